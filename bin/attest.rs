@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use azure_attest::{
+	enclaves::{
+		enclave_info::{Check, EnclaveInfo},
+		evidence::Evidence,
+		expectations::ExpectationMatcher,
+		model::{DataType, RuntimeData},
+	},
+	service::{client::ClientBuilder, error::AttestError},
+	AttestationResult, Config,
+};
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::{read_data, Output};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Tee {
+	Sgx,
+	Openenclave,
+	SevSnp,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DataKind {
+	Binary,
+	Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+	#[arg(value_enum)]
+	tee: Tee,
+
+	/// The SGX quote, OpenEnclave report or SEV-SNP evidence JSON, as hex, base64, binary or an
+	/// attest request JSON.
+	#[arg(long)]
+	quote: PathBuf,
+
+	/// Runtime data bound in the evidence, raw, hex or base64 encoded. Overrides that of a request
+	/// JSON.
+	#[arg(long)]
+	runtime_data: Option<PathBuf>,
+
+	#[arg(long, value_enum, default_value_t = DataKind::Binary)]
+	runtime_data_type: DataKind,
+
+	/// Enclave info JSON the attested enclave must match, SGX and OpenEnclave only.
+	#[arg(long)]
+	expect: Option<PathBuf>,
+
+	/// Expectations document, YAML or JSON, listing the enclaves and TEE type to accept.
+	#[arg(long)]
+	expectations: Option<PathBuf>,
+
+	/// JSON file with the provider `endpoint` and its `token`.
+	#[arg(long, default_value = ".config.json")]
+	config: PathBuf,
+
+	#[arg(long, value_enum, default_value_t = Output::Text)]
+	output: Output,
+}
+
+pub fn run(args: Args) -> Result<bool, String> {
+	let config = Config::from_file(&args.config.to_string_lossy())?;
+	let client = ClientBuilder::new(config.token.clone(), config.endpoint_url()?)
+		.failover_endpoints(config.failover_urls()?)
+		.build()?
+		.attestation_client();
+
+	let evidence = Evidence::load(&args.quote)?;
+	let runtime_data = match &args.runtime_data {
+		Some(path) => {
+			let data_type = match args.runtime_data_type {
+				DataKind::Binary => DataType::Binary,
+				DataKind::Json => DataType::Json,
+			};
+			Some(RuntimeData::new(azure_core::base64::encode(read_data(path)?), data_type))
+		},
+		None => evidence.runtime_data.clone(),
+	};
+	let expected = match &args.expect {
+		Some(_) if args.tee == Tee::SevSnp =>
+			return Err("--expect applies to SGX and OpenEnclave evidence only".into()),
+		Some(path) => Some(EnclaveInfo::from_file(&path.to_string_lossy())?),
+		None => None,
+	};
+	let matcher = match &args.expectations {
+		Some(path) => Some(ExpectationMatcher::from_file(&path.to_string_lossy())?),
+		None => None,
+	};
+
+	let result = match args.tee {
+		Tee::Sgx => {
+			let mut request = evidence.sgx_request();
+			request.runtime_data = runtime_data;
+			client.attest_sgx_enclave(request).into_result()
+		},
+		Tee::Openenclave => {
+			let mut request = evidence.open_enclave_request();
+			request.runtime_data = runtime_data;
+			client.attest_open_enclave(request).into_result()
+		},
+		Tee::SevSnp => {
+			let mut request = evidence.sev_snp_request();
+			request.runtime_data = runtime_data;
+			client.attest_sev_snp_vm(request).into_result()
+		},
+	};
+
+	let result = match result {
+		Ok(result) => result,
+		// The provider rejected the evidence, or its token did not verify.
+		Err(err @ (AttestError::Status { code: 400, .. } | AttestError::Token(_))) => {
+			match args.output {
+				Output::Text => println!("Attestation rejected: {}", err),
+				Output::Json =>
+					println!("{}", json!({ "passed": false, "error": err.to_string() })),
+			}
+			return Ok(false)
+		},
+		Err(err) => return Err(err.into()),
+	};
+
+	let mut checks = expected.map(|expected| expected.checks(&result)).unwrap_or_default();
+	if let Some(matcher) = &matcher {
+		checks.extend(matcher.checks(&result));
+	}
+	let passed = checks.iter().all(|check| check.passed);
+	match args.output {
+		Output::Text => print_text(&result, &checks, passed),
+		Output::Json => println!(
+			"{}",
+			serde_json::to_string_pretty(
+				&json!({ "passed": passed, "claims": result.claims, "checks": checks })
+			)
+			.map_err(|e| e.to_string())?
+		),
+	}
+	Ok(passed)
+}
+
+fn print_text(result: &AttestationResult, checks: &[Check], passed: bool) {
+	println!("Attestation {}", if passed { "passed" } else { "failed" });
+	for name in [
+		"x-ms-attestation-type",
+		"x-ms-sgx-mrenclave",
+		"x-ms-sgx-mrsigner",
+		"x-ms-sgx-svn",
+		"x-ms-sevsnpvm-launchmeasurement",
+		"x-ms-sevsnpvm-is-debuggable",
+		"iss",
+		"exp",
+	] {
+		if let Some(value) = result.claims.get(name) {
+			println!("    {:<34}: {}", name, value);
+		}
+	}
+	for check in checks {
+		println!("{:<35}: {}", format!("{} match", check.name), check.passed);
+		if !check.passed {
+			println!("    We think   : {}", check.expected);
+			println!("    MAA service: {}", check.actual);
+		}
+	}
+}
@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+
+use azure_attest::{
+	enclaves::{
+		enclave_info::EnclaveInfo,
+		evidence::Evidence,
+		model::{AttestationType, DataType, RuntimeData},
+	},
+	service::{
+		batch::{BatchAttestor, BatchItem, BatchReport, Outcome, DEFAULT_CONCURRENCY},
+		client::ClientBuilder,
+	},
+	Config,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+	attest::{DataKind, Tee},
+	read_data, Output,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+	/// Manifest JSON listing the evidence to attest. Paths in it are relative to the manifest.
+	manifest: PathBuf,
+
+	/// Attestations in flight at once. Overrides that of the manifest.
+	#[arg(long)]
+	concurrency: Option<usize>,
+
+	/// Attestations started per second at most. Overrides that of the manifest.
+	#[arg(long)]
+	rate_limit: Option<u32>,
+
+	/// JSON file with the provider `endpoint` and its `token`.
+	#[arg(long, default_value = ".config.json")]
+	config: PathBuf,
+
+	#[arg(long, value_enum, default_value_t = Output::Text)]
+	output: Output,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+	concurrency: Option<usize>,
+	rate_limit: Option<u32>,
+	items: Vec<ManifestItem>,
+}
+
+#[derive(Deserialize)]
+struct ManifestItem {
+	name: String,
+	/// `sgx`, `openenclave` or `sev-snp`, as for `attest`.
+	tee: String,
+	evidence: PathBuf,
+	runtime_data: Option<PathBuf>,
+	#[serde(default)]
+	runtime_data_type: Option<String>,
+	expect: Option<PathBuf>,
+}
+
+impl ManifestItem {
+	fn load(self, dir: &Path) -> Result<BatchItem, String> {
+		let tee = match Tee::from_str(&self.tee, true)? {
+			Tee::Sgx => AttestationType::SgxEnclave,
+			Tee::Openenclave => AttestationType::OpenEnclave,
+			Tee::SevSnp => AttestationType::SevSnpVm,
+		};
+		let mut evidence = Evidence::load(dir.join(&self.evidence))
+			.map_err(|e| format!("{}: {}", self.name, e))?;
+		if let Some(path) = &self.runtime_data {
+			let data_type = match self.runtime_data_type.as_deref() {
+				Some(kind) => DataKind::from_str(kind, true)?,
+				None => DataKind::Binary,
+			};
+			let data_type = match data_type {
+				DataKind::Binary => DataType::Binary,
+				DataKind::Json => DataType::Json,
+			};
+			let data = read_data(&dir.join(path))?;
+			evidence.runtime_data =
+				Some(RuntimeData::new(azure_core::base64::encode(data), data_type));
+		}
+		let expected = match &self.expect {
+			Some(_) if tee == AttestationType::SevSnpVm =>
+				return Err(format!(
+					"{}: expect applies to SGX and OpenEnclave evidence only",
+					self.name
+				)),
+			Some(path) => Some(EnclaveInfo::from_file(&dir.join(path).to_string_lossy())?),
+			None => None,
+		};
+
+		Ok(BatchItem { name: self.name, tee, evidence, expected })
+	}
+}
+
+pub fn run(args: Args) -> Result<bool, String> {
+	let manifest: Manifest = serde_json::from_slice(
+		&std::fs::read(&args.manifest)
+			.map_err(|e| format!("{}: {}", args.manifest.display(), e))?,
+	)
+	.map_err(|e| format!("Invalid manifest {}: {}", args.manifest.display(), e))?;
+	let dir = args.manifest.parent().unwrap_or(Path::new(""));
+	let items = manifest
+		.items
+		.into_iter()
+		.map(|item| item.load(dir))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let config = Config::from_file(&args.config.to_string_lossy())?;
+	let client = ClientBuilder::new(config.token.clone(), config.endpoint_url()?)
+		.failover_endpoints(config.failover_urls()?)
+		.build()?
+		.attestation_client();
+
+	let mut attestor = BatchAttestor::new(client)
+		.with_concurrency(args.concurrency.or(manifest.concurrency).unwrap_or(DEFAULT_CONCURRENCY));
+	if let Some(rate_limit) = args.rate_limit.or(manifest.rate_limit) {
+		attestor = attestor.with_rate_limit(rate_limit);
+	}
+	let report = attestor.run(&items);
+
+	match args.output {
+		Output::Text => print_text(&report),
+		Output::Json => println!(
+			"{}",
+			serde_json::to_string_pretty(&to_json(&report)).map_err(|e| e.to_string())?
+		),
+	}
+	Ok(report.results.iter().all(|result| result.passed()))
+}
+
+fn to_json(report: &BatchReport) -> serde_json::Value {
+	let results: Vec<serde_json::Value> = report
+		.results
+		.iter()
+		.map(|result| {
+			let mut item = json!({
+				"name": result.name,
+				"passed": result.passed(),
+				"checks": result.checks,
+				"duration_ms": result.duration.as_millis() as u64,
+			});
+			if let Outcome::Error(err) = &result.outcome {
+				item["error"] = json!(err);
+			}
+			item
+		})
+		.collect();
+	let summary = &report.summary;
+
+	json!({
+		"results": results,
+		"summary": {
+			"total": summary.total,
+			"passed": summary.passed,
+			"mismatched": summary.mismatched,
+			"errors": summary.errors,
+			"elapsed_ms": summary.elapsed.as_millis() as u64,
+		},
+	})
+}
+
+fn print_text(report: &BatchReport) {
+	for result in &report.results {
+		let status = match &result.outcome {
+			Outcome::Passed(_) => "passed".to_string(),
+			Outcome::Mismatch(_) => "mismatch".to_string(),
+			Outcome::Error(err) => format!("error: {}", err),
+		};
+		println!("{:<34}: {} ({} ms)", result.name, status, result.duration.as_millis());
+		for check in result.checks.iter().filter(|check| !check.passed) {
+			println!(
+				"    {} expected {}, MAA attested {}",
+				check.name, check.expected, check.actual
+			);
+		}
+	}
+
+	let summary = &report.summary;
+	println!(
+		"{} attested, {} passed, {} mismatched, {} errors in {:.1} s",
+		summary.total,
+		summary.passed,
+		summary.mismatched,
+		summary.errors,
+		summary.elapsed.as_secs_f64()
+	);
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use azure_attest::{
+	enclaves::{
+		draft_policy,
+		evidence::Evidence,
+		model::{DataType, RuntimeData},
+	},
+	service::client::ClientBuilder,
+	Config,
+};
+
+use crate::{attest::DataKind, read_data};
+
+#[derive(clap::Args)]
+pub struct Args {
+	/// File with the draft policy text.
+	policy: PathBuf,
+
+	/// The SGX quote to attest under both policies, as hex, base64, binary or an attest request
+	/// JSON.
+	#[arg(long)]
+	quote: PathBuf,
+
+	/// Runtime data bound in the quote, raw, hex or base64 encoded. Overrides that of a request
+	/// JSON.
+	#[arg(long)]
+	runtime_data: Option<PathBuf>,
+
+	#[arg(long, value_enum, default_value_t = DataKind::Binary)]
+	runtime_data_type: DataKind,
+
+	/// JSON file with the provider `endpoint` and its `token`.
+	#[arg(long, default_value = ".config.json")]
+	config: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<bool, String> {
+	let config = Config::from_file(&args.config.to_string_lossy())?;
+	let client = ClientBuilder::new(config.token.clone(), config.endpoint_url()?)
+		.failover_endpoints(config.failover_urls()?)
+		.build()?
+		.attestation_client();
+
+	let policy = std::fs::read_to_string(&args.policy)
+		.map_err(|e| format!("{}: {}", args.policy.display(), e))?;
+	let evidence = Evidence::load(&args.quote)?;
+	let runtime_data = match &args.runtime_data {
+		Some(path) => {
+			let data_type = match args.runtime_data_type {
+				DataKind::Binary => DataType::Binary,
+				DataKind::Json => DataType::Json,
+			};
+			Some(RuntimeData::new(azure_core::base64::encode(read_data(path)?), data_type))
+		},
+		None => evidence.runtime_data.clone(),
+	};
+
+	let changes = draft_policy::diff(&client, &evidence, runtime_data, &policy)?;
+	if changes.is_empty() {
+		println!("The draft policy issues the same claims as the current policy");
+	}
+	for change in changes {
+		println!("{}", change);
+	}
+	Ok(true)
+}
@@ -1,13 +1,78 @@
-use azure_attest::{
-	enclaves::{sgx_enclave::SgxEnclave, *},
-	EnclaveType, MAA,
-};
+mod attest;
+mod batch;
+mod draft_diff;
+mod quote;
+#[cfg(feature = "server")]
+mod serve;
+mod token;
+
+use std::path::Path;
+
+use azure_attest::enclaves::evidence;
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Exit code of a run whose attestation was rejected, or did not meet the expectations.
+pub const EXIT_REJECTED: i32 = 1;
+
+/// Exit code of a run that could not complete, e.g. an unreadable file or unreachable provider.
+/// Also used by clap for usage errors.
+pub const EXIT_ERROR: i32 = 2;
+
+#[derive(Parser)]
+#[command(name = "azure-attest", about = "Attest TEE evidence with Microsoft Azure Attestation")]
+struct Cli {
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Attest evidence and verify the issued token.
+	Attest(attest::Args),
+	/// Attest many pieces of evidence listed in a manifest, concurrently.
+	Batch(batch::Args),
+	/// Decode and verify attestation tokens.
+	#[command(subcommand)]
+	Token(token::Command),
+	/// Inspect evidence files.
+	#[command(subcommand)]
+	Quote(quote::Command),
+	/// Serve token verification and attestation over HTTP, for relying parties.
+	#[cfg(feature = "server")]
+	Serve(serve::Args),
+	/// Print how the claims issued under a draft policy differ from the current policy.
+	DraftDiff(draft_diff::Args),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+	Text,
+	Json,
+}
+
+/// Data from `path`, hex or base64 encoded or raw, see `evidence::decode`.
+pub fn read_data(path: &Path) -> Result<Vec<u8>, String> {
+	Ok(evidence::read(path)?.1)
+}
 
 fn main() {
-	let enclave = EnclaveType::TestEnclave;
-	match enclave {
-		EnclaveType::SgxEnclave => SgxEnclave.azure_attest(),
-		EnclaveType::TestEnclave => test_enclave::TestEnclave.azure_attest(),
-		EnclaveType::OpenEnclave => open_enclave::verify(),
+	let cli = Cli::parse();
+	let passed = match cli.command {
+		Command::Attest(args) => attest::run(args),
+		Command::Batch(args) => batch::run(args),
+		Command::Token(command) => token::run(command),
+		Command::Quote(command) => quote::run(command),
+		#[cfg(feature = "server")]
+		Command::Serve(args) => serve::run(args),
+		Command::DraftDiff(args) => draft_diff::run(args),
+	};
+
+	match passed {
+		Ok(true) => {},
+		Ok(false) => std::process::exit(EXIT_REJECTED),
+		Err(err) => {
+			eprintln!("error: {}", err);
+			std::process::exit(EXIT_ERROR)
+		},
 	}
 }
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use azure_attest::enclaves::evidence::Evidence;
+use clap::Subcommand;
+use serde_json::json;
+
+use crate::Output;
+
+#[derive(Subcommand)]
+pub enum Command {
+	/// Detect the encoding and type of evidence, and print its parsed fields.
+	Inspect {
+		/// An SGX or TDX quote, OpenEnclave report or SEV-SNP report, as hex, base64, binary or an
+		/// attest request JSON.
+		file: PathBuf,
+
+		#[arg(long, value_enum, default_value_t = Output::Text)]
+		output: Output,
+	},
+}
+
+pub fn run(command: Command) -> Result<bool, String> {
+	let Command::Inspect { file, output } = command;
+	let evidence = Evidence::load(&file)?;
+	let (encoding, bytes) = (evidence.encoding, &evidence.quote);
+	let evidence = evidence.parse()?;
+
+	match output {
+		Output::Text => {
+			println!("{}, {} encoded, {} bytes", evidence.kind(), encoding, bytes.len());
+			for (name, value) in evidence.fields() {
+				println!("    {:<20}: {}", name, value);
+			}
+		},
+		Output::Json => {
+			let mut json = evidence.to_json();
+			json["encoding"] = json!(encoding.to_string());
+			json["size"] = json!(bytes.len());
+			println!("{}", serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?);
+		},
+	}
+	Ok(true)
+}
@@ -0,0 +1,36 @@
+use std::{
+	net::{SocketAddr, TcpListener},
+	path::PathBuf,
+};
+
+use azure_attest::{
+	service::{
+		client::ClientBuilder,
+		server::{serve, VerificationService},
+	},
+	Config,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+	/// Address to listen on.
+	#[arg(long, default_value = "127.0.0.1:8080")]
+	listen: SocketAddr,
+
+	/// JSON file with the provider `endpoint` and its `token`.
+	#[arg(long, default_value = ".config.json")]
+	config: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<bool, String> {
+	let config = Config::from_file(&args.config.to_string_lossy())?;
+	let client = ClientBuilder::new(config.token.clone(), config.endpoint_url()?)
+		.failover_endpoints(config.failover_urls()?)
+		.build()?;
+
+	let listener = TcpListener::bind(args.listen).map_err(|e| format!("{}: {}", args.listen, e))?;
+	eprintln!("Serving on http://{}", args.listen);
+	let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+	runtime.block_on(serve(listener, VerificationService::new(client)))?;
+	Ok(true)
+}
@@ -0,0 +1,172 @@
+use std::{
+	fs,
+	io::{self, Read},
+	path::PathBuf,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use azure_attest::{
+	service::{
+		signing_keys::SigningKeyCache,
+		token::{decode_token, issuer_endpoint, verify_token, Jws},
+	},
+	AttestationResult,
+};
+use clap::Subcommand;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+use url::Url;
+
+use crate::Output;
+
+#[derive(Subcommand)]
+pub enum Command {
+	/// Print the header, claims and signature status of a token, without verifying it.
+	Decode {
+		/// A token, or a MAA response JSON carrying one. Read from stdin when absent or `-`.
+		input: Option<PathBuf>,
+
+		#[arg(long, value_enum, default_value_t = Output::Text)]
+		output: Output,
+	},
+	/// Print the typed attestation result of a token, verifying it against its provider.
+	Verify {
+		/// The provider that issued the token, repeated to accept any of several providers.
+		/// Without it, the signature and issuer are not verified.
+		#[arg(long)]
+		endpoint: Vec<Url>,
+
+		/// A token, or a MAA response JSON carrying one. Read from stdin when absent or `-`.
+		input: Option<PathBuf>,
+
+		#[arg(long, value_enum, default_value_t = Output::Text)]
+		output: Output,
+	},
+}
+
+// The token in `input`: a bare token, a JSON string, or a MAA response `{"token": ...}`.
+fn read_token(input: Option<&PathBuf>) -> Result<String, String> {
+	let text = match input {
+		Some(path) if path.as_os_str() != "-" =>
+			fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+		_ => {
+			let mut text = String::new();
+			io::stdin().read_to_string(&mut text).map_err(|e| e.to_string())?;
+			text
+		},
+	};
+
+	let text = text.trim();
+	if !text.starts_with('{') && !text.starts_with('"') {
+		return Ok(text.to_string())
+	}
+	match serde_json::from_str(text).map_err(|e| e.to_string())? {
+		Value::String(token) => Ok(token),
+		Value::Object(response) => match response.get("token") {
+			Some(Value::String(token)) => Ok(token.clone()),
+			_ => Err("Response JSON has no token".into()),
+		},
+		_ => Err("Input is neither a token nor a response JSON".into()),
+	}
+}
+
+fn now() -> Result<u64, String> {
+	Ok(SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_err(|e| e.to_string())?
+		.as_secs())
+}
+
+// When the token expires, and whether it has.
+fn describe_expiry(exp: Option<u64>) -> Result<(String, bool), String> {
+	let Some(exp) = exp else { return Ok(("never".into(), false)) };
+	let at = OffsetDateTime::from_unix_timestamp(exp as i64).map_err(|e| e.to_string())?;
+	let now = now()?;
+	if exp <= now {
+		Ok((format!("{} (EXPIRED {}s ago)", at, now - exp), true))
+	} else {
+		Ok((format!("{} (in {}s)", at, exp - now), false))
+	}
+}
+
+pub fn run(command: Command) -> Result<bool, String> {
+	match command {
+		Command::Decode { input, output } => decode(&read_token(input.as_ref())?, output),
+		Command::Verify { endpoint, input, output } =>
+			verify(&read_token(input.as_ref())?, endpoint, output),
+	}
+}
+
+fn decode(token: &str, output: Output) -> Result<bool, String> {
+	let jws = Jws::parse(token)?;
+	let claims: Value = serde_json::from_slice(&jws.claims).map_err(|e| e.to_string())?;
+	let signature = match jws.header.alg.as_str() {
+		"none" => "unsigned".to_string(),
+		alg => format!("{}, {} bytes, not verified", alg, jws.signature.len()),
+	};
+	let (expiry, expired) = describe_expiry(claims["exp"].as_u64())?;
+
+	match output {
+		Output::Text => {
+			let header = serde_json::to_string_pretty(&jws.header).map_err(|e| e.to_string())?;
+			let claims = serde_json::to_string_pretty(&claims).map_err(|e| e.to_string())?;
+			println!("Header:\n{}\nClaims:\n{}", header, claims);
+			println!("Signature: {}", signature);
+			println!("Expires  : {}", expiry);
+		},
+		Output::Json => println!(
+			"{}",
+			json!({
+				"header": jws.header,
+				"claims": claims,
+				"signature": signature,
+				"expired": expired,
+			})
+		),
+	}
+	Ok(true)
+}
+
+fn verify(token: &str, endpoints: Vec<Url>, output: Output) -> Result<bool, String> {
+	let (result, verified): (Result<AttestationResult, String>, bool) = if endpoints.is_empty() {
+		(decode_token(token), false)
+	} else {
+		let result = issuer_endpoint(token, &endpoints)
+			.and_then(|endpoint| verify_token(token, endpoint, &SigningKeyCache::new()));
+		(result, true)
+	};
+	let result = match result {
+		Ok(result) => result,
+		Err(err) => {
+			match output {
+				Output::Text => println!("Token verification failed: {}", err),
+				Output::Json => println!("{}", json!({ "verified": false, "error": err })),
+			}
+			return Ok(false)
+		},
+	};
+	let (expiry, expired) = describe_expiry(result.exp)?;
+
+	match output {
+		Output::Text => {
+			println!("{:#?}", result);
+			if verified {
+				let issuer = result.iss.as_deref().unwrap_or_default();
+				println!("Signature: verified with the keys of {}", issuer);
+			} else {
+				println!("Signature: NOT verified, pass --endpoint to verify it");
+			}
+			println!("Expires  : {}", expiry);
+		},
+		Output::Json => println!(
+			"{}",
+			json!({
+				"verified": verified,
+				"expired": expired,
+				"result": result,
+				"claims": result.claims,
+			})
+		),
+	}
+	Ok(!expired)
+}
@@ -0,0 +1,46 @@
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Encode as padded base64.
+pub fn base64_encode(data: impl AsRef<[u8]>) -> String {
+	STANDARD.encode(data)
+}
+
+/// Decode padded base64.
+pub fn base64_decode(data: impl AsRef<[u8]>) -> Result<Vec<u8>, String> {
+	STANDARD.decode(data).map_err(|e| e.to_string())
+}
+
+/// Decode unpadded base64url, as used by JSON Web Tokens.
+pub fn base64url_decode(data: &str) -> Result<Vec<u8>, String> {
+	let mut data = data.trim_end_matches('=').replace('-', "+").replace('_', "/");
+	let padding = (4 - data.len() % 4) % 4;
+	data.push_str(&"=".repeat(padding));
+	base64_decode(data)
+}
+
+/// Encode as unpadded base64url, as used by JSON Web Tokens.
+pub fn base64url(data: &[u8]) -> String {
+	base64_encode(data).trim_end_matches('=').replace('+', "-").replace('/', "_")
+}
+
+fn jws_part(token: &str, index: usize) -> Result<Vec<u8>, String> {
+	let parts: Vec<&str> = token.trim().split('.').collect();
+	if parts.len() != 3 {
+		return Err("JSON Web Tokens must have 3 components delimited by '.' characters.".into())
+	}
+	base64url_decode(parts[index])
+}
+
+/// The decoded protected header of a JWS in compact serialization.
+pub fn jws_header(token: &str) -> Result<Vec<u8>, String> {
+	jws_part(token, 0)
+}
+
+/// The decoded payload of a JWS in compact serialization, without verifying anything about it.
+pub fn jws_payload(token: &str) -> Result<Vec<u8>, String> {
+	jws_part(token, 1)
+}
@@ -0,0 +1,21 @@
+//! The attestation models of azure-attest: requests, tokens claims and quote parsing, without the
+//! HTTP stack.
+//!
+//! Builds `no_std` with `alloc` when the default `std` feature is disabled, for use inside
+//! enclaves.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod encoding;
+pub mod maa;
+pub mod model;
+pub mod quote;
+pub mod report;
+pub mod result;
+pub mod snp;
+
+pub use encoding::{base64url, base64url_decode};
+pub use report::AttestationReport;
+pub use result::AttestationResult;
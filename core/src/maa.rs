@@ -0,0 +1,30 @@
+use alloc::string::String;
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+// The legacy SGX claims, absent from tokens of other TEEs than SGX, e.g. SEV-SNP.
+#[derive(Debug, Default, Serialize, Deserialize, Decode, Encode)]
+pub struct MAAPolicy {
+	#[serde(rename = "is-debuggable", default, skip_serializing_if = "Option::is_none")]
+	pub is_debuggable: Option<bool>,
+
+	#[serde(rename = "product-id", default, skip_serializing_if = "Option::is_none")]
+	pub product_id: Option<u32>,
+
+	#[serde(rename = "sgx-mrenclave", default, skip_serializing_if = "Option::is_none")]
+	pub sgx_mrenclave: Option<String>,
+
+	#[serde(rename = "sgx-mrsigner", default, skip_serializing_if = "Option::is_none")]
+	pub sgx_mrsigner: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub svn: Option<u32>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub tee: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Decode, Encode)]
+pub struct MAAResponse {
+	pub token: String,
+}
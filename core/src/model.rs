@@ -0,0 +1,466 @@
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::fmt;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::{base64_decode, base64_encode, base64url_decode, jws_payload};
+
+#[doc = "Specifies the type of the data encoded contained within the \"data\" field of a \"RuntimeData\" or \"InitTimeData\" object"]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DataType {
+	#[default]
+	Binary,
+	#[serde(rename = "JSON")]
+	Json,
+}
+
+#[doc = "Initialization time data are a conduit for any configuration information that is unknown when building the Trusted Execution Environment (TEE) and is defined at TEE launch time. This data can be used with confidential container or VM scenarios to capture configuration settings such as disk volume content, network configuration, etc."]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct InitTimeData {
+	#[doc = "Initialization time data are passed into the Trusted Execution Environment (TEE) when it is created. For an Icelake SGX quote, the SHA256 hash of the InitTimeData must match the lower 32 bytes of the quote's \"config id\" attribute. For a SEV-SNP quote, the SHA256 hash of the InitTimeData must match the quote's \"host data\" attribute."]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub data: Option<String>,
+	#[doc = "Specifies the type of the data encoded contained within the \"data\" field of a \"RuntimeData\" or \"InitTimeData\" object"]
+	#[serde(rename = "dataType", default, skip_serializing_if = "Option::is_none")]
+	pub data_type: Option<DataType>,
+}
+impl InitTimeData {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Binary init-time data, which MAA will emit base64url encoded as the `x-ms-inittime` claim.
+	pub fn binary(data: &[u8]) -> Self {
+		Self { data: Some(base64_encode(data)), data_type: Some(DataType::Binary) }
+	}
+
+	/// JSON init-time data from `value`, serialized canonically like `RuntimeData::json`.
+	pub fn json<T: Serialize>(value: &T) -> Result<Self, String> {
+		let data = canonical_json(value)?;
+		Ok(Self { data: Some(base64_encode(data)), data_type: Some(DataType::Json) })
+	}
+
+	/// The raw init-time data, as the host hashes it.
+	pub fn bytes(&self) -> Result<Vec<u8>, String> {
+		let data = self.data.as_ref().ok_or("Init-time data has no data")?;
+		base64_decode(data)
+	}
+
+	/// SHA-256 of the init-time data, which must be the lower 32 bytes of the SGX CONFIGID or the
+	/// SEV-SNP HOST_DATA of the TEE.
+	pub fn hash(&self) -> Result<[u8; 32], String> {
+		let mut hash = [0u8; 32];
+		hash.copy_from_slice(digest(&SHA256, &self.bytes()?).as_ref());
+		Ok(hash)
+	}
+}
+
+// `value` as compact JSON with the keys of every object sorted, whatever the order `serde_json`
+// keeps them in: with its `preserve_order` feature enabled anywhere in the build, that is the
+// insertion order.
+fn canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+	fn write(value: &serde_json::Value, out: &mut Vec<u8>) -> Result<(), String> {
+		match value {
+			serde_json::Value::Object(map) => {
+				let mut entries: Vec<_> = map.iter().collect();
+				entries.sort_by(|a, b| a.0.cmp(b.0));
+				out.push(b'{');
+				for (i, (key, value)) in entries.into_iter().enumerate() {
+					if i > 0 {
+						out.push(b',');
+					}
+					out.extend(serde_json::to_vec(key).map_err(|e| e.to_string())?);
+					out.push(b':');
+					write(value, out)?;
+				}
+				out.push(b'}');
+			},
+			serde_json::Value::Array(values) => {
+				out.push(b'[');
+				for (i, value) in values.iter().enumerate() {
+					if i > 0 {
+						out.push(b',');
+					}
+					write(value, out)?;
+				}
+				out.push(b']');
+			},
+			value => out.extend(serde_json::to_vec(value).map_err(|e| e.to_string())?),
+		}
+		Ok(())
+	}
+
+	let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+	let mut out = Vec::new();
+	write(&value, &mut out)?;
+	Ok(out)
+}
+
+#[doc = "Runtime data are a conduit for any information defined by the Trusted Execution Environment (TEE) when actually running."]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct RuntimeData {
+	pub data: String, // base64 format
+	#[serde(rename = "dataType")]
+	pub data_type: DataType,
+}
+
+impl RuntimeData {
+	pub fn new(data: String, data_type: DataType) -> RuntimeData {
+		Self { data, data_type }
+	}
+
+	/// JSON runtime data from `value`, which MAA will emit as the `x-ms-runtime` claim.
+	///
+	/// `value` is serialized canonically, compact and with object keys sorted bytewise, so that the
+	/// enclave and the relying party compute the same `report_data_hash` for the same value.
+	/// Numbers are not canonicalized: non-integer floats are written as `serde_json` formats them.
+	pub fn json<T: Serialize>(value: &T) -> Result<RuntimeData, String> {
+		let data = canonical_json(value)?;
+		Ok(Self::new(base64_encode(data), DataType::Json))
+	}
+
+	/// The raw runtime data, as the enclave hashes it.
+	pub fn bytes(&self) -> Result<Vec<u8>, String> {
+		base64_decode(&self.data)
+	}
+
+	/// SHA-256 of the runtime data, which the enclave must put in the first 32 bytes of its
+	/// REPORTDATA for MAA to accept the runtime data.
+	pub fn report_data_hash(&self) -> Result<[u8; 32], String> {
+		let mut hash = [0u8; 32];
+		hash.copy_from_slice(digest(&SHA256, &self.bytes()?).as_ref());
+		Ok(hash)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AttestationRequest {
+	pub(crate) quote: String,
+
+	#[serde(rename = "runtimeData")]
+	pub(crate) runtime_data: Option<RuntimeData>,
+}
+
+impl AttestationRequest {
+	pub fn new(quote: String, runtime_data: Option<RuntimeData>) -> AttestationRequest {
+		Self { quote, runtime_data }
+	}
+}
+
+#[doc = "Attestation request for Intel SGX enclaves"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct AttestSgxEnclaveRequest {
+	#[doc = "Quote of the enclave to be attested"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub quote: Option<String>,
+
+	#[doc = "Runtime data are a conduit for any information defined by the Trusted Execution Environment (TEE) when actually running."]
+	#[serde(rename = "runtimeData", default, skip_serializing_if = "Option::is_none")]
+	pub runtime_data: Option<RuntimeData>,
+
+	#[doc = "Initialization time data are a conduit for any configuration information that is unknown when building the Trusted Execution Environment (TEE) and is defined at TEE launch time. This data can be used with confidential container or VM scenarios to capture configuration settings such as disk volume content, network configuration, etc."]
+	#[serde(rename = "initTimeData", default, skip_serializing_if = "Option::is_none")]
+	pub init_time_data: Option<InitTimeData>,
+
+	#[doc = "Attest against the provided draft policy. Note that the resulting token cannot be validated."]
+	#[serde(
+		rename = "draftPolicyForAttestation",
+		default,
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub draft_policy_for_attestation: Option<String>,
+
+	#[doc = "Nonce for incoming request - emitted in the generated attestation token"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub nonce: Option<String>,
+}
+impl AttestSgxEnclaveRequest {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[doc = "The result of an attestation operation"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct AttestationResponse {
+	#[doc = "An RFC 7519 Json Web Token"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub token: Option<JsonWebToken>,
+}
+impl AttestationResponse {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+pub type JsonWebToken = String;
+
+#[doc = "Attestation request for Intel SGX enclaves"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct AttestOpenEnclaveRequest {
+	#[doc = "OpenEnclave report from the enclave to be attested"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub report: Option<String>,
+	#[doc = "Runtime data are a conduit for any information defined by the Trusted Execution Environment (TEE) when actually running."]
+	#[serde(rename = "runtimeData", default, skip_serializing_if = "Option::is_none")]
+	pub runtime_data: Option<RuntimeData>,
+	#[doc = "Initialization time data are a conduit for any configuration information that is unknown when building the Trusted Execution Environment (TEE) and is defined at TEE launch time. This data can be used with confidential container or VM scenarios to capture configuration settings such as disk volume content, network configuration, etc."]
+	#[serde(rename = "initTimeData", default, skip_serializing_if = "Option::is_none")]
+	pub init_time_data: Option<InitTimeData>,
+	#[doc = "Attest against the provided draft policy. Note that the resulting token cannot be validated."]
+	#[serde(
+		rename = "draftPolicyForAttestation",
+		default,
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub draft_policy_for_attestation: Option<String>,
+	#[doc = "Nonce for incoming request - emitted in the generated attestation token"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub nonce: Option<String>,
+}
+impl AttestOpenEnclaveRequest {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[doc = "Attestation request for AMD SEV-SNP confidential VMs"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct AttestSevSnpVmRequest {
+	#[doc = "The SEV-SNP evidence of the VM, the JSON of its attestation report and VCEK certificate chain, base64url encoded"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub report: Option<String>,
+	#[doc = "Runtime data are a conduit for any information defined by the Trusted Execution Environment (TEE) when actually running."]
+	#[serde(rename = "runtimeData", default, skip_serializing_if = "Option::is_none")]
+	pub runtime_data: Option<RuntimeData>,
+	#[doc = "Initialization time data, whose SHA256 hash must match the HOST_DATA of the report."]
+	#[serde(rename = "initTimeData", default, skip_serializing_if = "Option::is_none")]
+	pub init_time_data: Option<InitTimeData>,
+	#[doc = "Attest against the provided draft policy. Note that the resulting token cannot be validated."]
+	#[serde(
+		rename = "draftPolicyForAttestation",
+		default,
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub draft_policy_for_attestation: Option<String>,
+	#[doc = "Nonce for incoming request - emitted in the generated attestation token"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub nonce: Option<String>,
+}
+impl AttestSevSnpVmRequest {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[doc = "The type of attestation a policy applies to"]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AttestationType {
+	SgxEnclave,
+	OpenEnclave,
+	Tpm,
+	SevSnpVm,
+}
+
+impl fmt::Display for AttestationType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			AttestationType::SgxEnclave => "SgxEnclave",
+			AttestationType::OpenEnclave => "OpenEnclave",
+			AttestationType::Tpm => "Tpm",
+			AttestationType::SevSnpVm => "SevSnpVm",
+		};
+		f.write_str(name)
+	}
+}
+
+#[doc = "The body of a set policy request: either plain policy text, which is sent as an unsigned JWS, or a JWS already wrapping the policy and signed by a policy signing key"]
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttestationPolicy {
+	Text(String),
+	SignedJws(String),
+}
+
+#[doc = "The payload of a policy JWS, as stored by the attestation service"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct StoredAttestationPolicy {
+	#[doc = "Policy text, base64url encoded"]
+	#[serde(rename = "AttestationPolicy", default, skip_serializing_if = "Option::is_none")]
+	pub attestation_policy: Option<String>,
+}
+
+#[doc = "The result of a policy modification"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PolicyModification {
+	Updated,
+	Removed,
+}
+
+#[doc = "A JSON Web Key, RFC 7517"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct JsonWebKey {
+	pub kty: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub kid: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub alg: Option<String>,
+	#[serde(rename = "use", default, skip_serializing_if = "Option::is_none")]
+	pub use_: Option<String>,
+	#[doc = "RSA modulus, base64url encoded"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub n: Option<String>,
+	#[doc = "RSA public exponent, base64url encoded"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub e: Option<String>,
+	#[doc = "Elliptic curve name, e.g. P-256"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub crv: Option<String>,
+	#[doc = "Elliptic curve point x coordinate, base64url encoded"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub x: Option<String>,
+	#[doc = "Elliptic curve point y coordinate, base64url encoded"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub y: Option<String>,
+	#[doc = "Certificate chain, base64 (not url) encoded DER, leaf first"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub x5c: Option<Vec<String>>,
+}
+
+#[doc = "The response to a policy or policy certificate management operation"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct PolicyResponse {
+	#[doc = "An RFC 7519 Json Web Token whose claims are a `PolicyResult`"]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub token: Option<JsonWebToken>,
+}
+
+#[doc = "A set of JSON Web Keys, RFC 7517"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct JsonWebKeySet {
+	pub keys: Vec<JsonWebKey>,
+}
+
+impl JsonWebKey {
+	#[doc = "The certificate thumbprint the service uses to identify the key: the hex encoded SHA1 hash of the leaf certificate in `x5c`."]
+	pub fn thumbprint(&self) -> Result<Option<String>, String> {
+		let Some(cert) = self.x5c.as_ref().and_then(|x5c| x5c.first()) else { return Ok(None) };
+		let cert = base64_decode(cert)?;
+		let digest = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, &cert);
+		Ok(Some(hex::encode_upper(digest)))
+	}
+}
+
+#[doc = "The body of a policy certificate add or remove request, wrapped in a JWS signed by a policy management key"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct PolicyCertificateBody {
+	#[serde(rename = "policyCertificate")]
+	pub policy_certificate: JsonWebKey,
+}
+
+#[doc = "The result of listing the policy management certificates"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct PolicyCertificatesResult {
+	#[serde(rename = "x-ms-policy-certificates", default, skip_serializing_if = "Option::is_none")]
+	pub policy_certificates: Option<JsonWebKeySet>,
+}
+
+#[doc = "Whether a policy management certificate is present after a modification"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CertificateModification {
+	IsPresent,
+	IsAbsent,
+}
+
+#[doc = "The result of adding or removing a policy management certificate"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct PolicyCertificatesModificationResult {
+	#[doc = "Hex encoded SHA1 thumbprint of the certificate"]
+	#[serde(
+		rename = "x-ms-certificate-thumbprint",
+		default,
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub certificate_thumbprint: Option<String>,
+	#[serde(
+		rename = "x-ms-policycertificates-result",
+		default,
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub certificate_resolution: Option<CertificateModification>,
+}
+
+#[doc = "The result of a policy get, set or reset operation, decoded from the token returned by the service"]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct PolicyResult {
+	#[doc = "Whether the policy was updated or removed, absent for get"]
+	#[serde(rename = "x-ms-policy-result", default, skip_serializing_if = "Option::is_none")]
+	pub policy_resolution: Option<PolicyModification>,
+	#[doc = "The SHA256 hash of the policy token, base64url encoded"]
+	#[serde(rename = "x-ms-policy-token-hash", default, skip_serializing_if = "Option::is_none")]
+	pub policy_token_hash: Option<String>,
+	#[doc = "The key that signed the policy, absent for unsigned policies"]
+	#[serde(rename = "x-ms-policy-signer", default, skip_serializing_if = "Option::is_none")]
+	pub policy_signer: Option<JsonWebKey>,
+	#[doc = "The policy token, a JWS wrapping a `StoredAttestationPolicy`"]
+	#[serde(rename = "x-ms-policy", default, skip_serializing_if = "Option::is_none")]
+	pub policy: Option<JsonWebToken>,
+}
+
+impl PolicyResult {
+	#[doc = "Decode the policy text out of the policy token."]
+	pub fn policy_text(&self) -> Result<Option<String>, String> {
+		let Some(policy) = &self.policy else { return Ok(None) };
+		let stored: StoredAttestationPolicy =
+			serde_json::from_slice(&jws_payload(policy)?).map_err(|e| e.to_string())?;
+		let Some(text) = stored.attestation_policy else { return Ok(None) };
+		let text = base64url_decode(&text)?;
+		String::from_utf8(text).map(Some).map_err(|e| e.to_string())
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	#[derive(Serialize)]
+	struct Keys {
+		z: u32,
+		a: &'static str,
+		m: Vec<serde_json::Value>,
+	}
+
+	#[test]
+	fn json_runtime_data_is_canonical() {
+		let nested = serde_json::json!({ "y": 2.5, "b": null });
+		let runtime_data = RuntimeData::json(&Keys { z: 1, a: "key", m: vec![nested] }).unwrap();
+		let canonical = br#"{"a":"key","m":[{"b":null,"y":2.5}],"z":1}"#;
+		assert_eq!(runtime_data.bytes().unwrap(), canonical);
+		assert_eq!(
+			hex::encode(runtime_data.report_data_hash().unwrap()),
+			hex::encode(digest(&SHA256, canonical))
+		);
+		let init_time_data = InitTimeData::json(&Keys { z: 1, a: "key", m: vec![] }).unwrap();
+		assert_eq!(init_time_data.bytes().unwrap(), br#"{"a":"key","m":[],"z":1}"#);
+
+		let json = serde_json::to_value(&runtime_data).unwrap();
+		assert_eq!(json["dataType"], "JSON");
+	}
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Runtime {
+		a: String,
+	}
+
+	#[test]
+	fn runtime_claim_is_deserialized() {
+		let result = crate::result::AttestationResult {
+			x_ms_runtime: Some(serde_json::json!({ "a": "key", "z": 1 })),
+			..Default::default()
+		};
+		assert_eq!(result.runtime::<Runtime>().unwrap(), Runtime { a: "key".into() });
+		assert!(crate::result::AttestationResult::default().runtime::<Runtime>().is_err());
+	}
+}
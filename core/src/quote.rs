@@ -0,0 +1,260 @@
+//! Parsing of Intel SGX and TDX DCAP quotes (versions 3 and 4) and OpenEnclave remote reports.
+
+use alloc::{format, string::String, vec::Vec};
+
+pub const QUOTE_HEADER_SIZE: usize = 48;
+pub const REPORT_BODY_SIZE: usize = 384;
+
+pub const TD_REPORT_BODY_SIZE: usize = 584;
+
+/// `tee_type` of a version 4 quote of an SGX enclave; version 3 quotes leave it zero.
+pub const TEE_TYPE_SGX: u32 = 0x0000_0000;
+
+/// `tee_type` of a version 4 quote of a TDX trust domain.
+pub const TEE_TYPE_TDX: u32 = 0x0000_0081;
+
+// In SGX, the DEBUG flag is 0x0000000000000002ULL.
+const SGX_FLAGS_DEBUG: u64 = 0x2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuoteHeader {
+	pub version: u16,
+	pub att_key_type: u16,
+	pub tee_type: u32,
+	pub qe_svn: u16,
+	pub pce_svn: u16,
+	pub qe_vendor_id: [u8; 16],
+	pub user_data: [u8; 20],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attributes {
+	pub flags: u64,
+	pub xfrm: u64,
+}
+
+#[doc = "The `sgx_report_body_t` of the attested enclave"]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportBody {
+	pub cpu_svn: [u8; 16],
+	pub misc_select: u32,
+	pub isv_ext_prod_id: [u8; 16],
+	pub attributes: Attributes,
+	pub mr_enclave: [u8; 32],
+	pub mr_signer: [u8; 32],
+	pub config_id: [u8; 64],
+	pub isv_prod_id: u16,
+	pub isv_svn: u16,
+	pub config_svn: u16,
+	pub isv_family_id: [u8; 16],
+	pub report_data: [u8; 64],
+}
+
+impl ReportBody {
+	pub fn is_debuggable(&self) -> bool {
+		self.attributes.flags & SGX_FLAGS_DEBUG != 0
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SgxQuote {
+	pub header: QuoteHeader,
+	pub report_body: ReportBody,
+	/// The quote signature data: ECDSA signature, attestation key and QE certification data.
+	pub signature_data: Vec<u8>,
+}
+
+fn array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+	let mut array = [0u8; N];
+	array.copy_from_slice(&bytes[offset..offset + N]);
+	array
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes(array(bytes, offset))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(array(bytes, offset))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+	u64::from_le_bytes(array(bytes, offset))
+}
+
+impl QuoteHeader {
+	pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() < QUOTE_HEADER_SIZE {
+			return Err(format!("Quote is too short for its header: {} bytes", bytes.len()))
+		}
+		Ok(QuoteHeader {
+			version: u16_at(bytes, 0),
+			att_key_type: u16_at(bytes, 2),
+			tee_type: u32_at(bytes, 4),
+			qe_svn: u16_at(bytes, 8),
+			pce_svn: u16_at(bytes, 10),
+			qe_vendor_id: array(bytes, 12),
+			user_data: array(bytes, 28),
+		})
+	}
+}
+
+impl ReportBody {
+	pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() < REPORT_BODY_SIZE {
+			return Err(format!("Report body is too short: {} bytes", bytes.len()))
+		}
+		Ok(ReportBody {
+			cpu_svn: array(bytes, 0),
+			misc_select: u32_at(bytes, 16),
+			isv_ext_prod_id: array(bytes, 32),
+			attributes: Attributes { flags: u64_at(bytes, 48), xfrm: u64_at(bytes, 56) },
+			mr_enclave: array(bytes, 64),
+			mr_signer: array(bytes, 128),
+			config_id: array(bytes, 192),
+			isv_prod_id: u16_at(bytes, 256),
+			isv_svn: u16_at(bytes, 258),
+			config_svn: u16_at(bytes, 260),
+			isv_family_id: array(bytes, 304),
+			report_data: array(bytes, 320),
+		})
+	}
+}
+
+impl SgxQuote {
+	/// Parse a DCAP quote of an SGX enclave.
+	pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+		let header = QuoteHeader::parse(bytes)?;
+		if header.version != 3 && header.version != 4 {
+			return Err(format!("Unsupported quote version {}", header.version))
+		}
+		if header.version == 4 && header.tee_type != TEE_TYPE_SGX {
+			return Err(format!("Not an SGX quote, tee type {:#x}", header.tee_type))
+		}
+
+		let report_body = ReportBody::parse(&bytes[QUOTE_HEADER_SIZE..])?;
+
+		let offset = QUOTE_HEADER_SIZE + REPORT_BODY_SIZE;
+		if bytes.len() < offset + 4 {
+			return Err("Quote has no signature data".into())
+		}
+		let signature_len = u32_at(bytes, offset) as usize;
+		let signature_data = bytes
+			.get(offset + 4..offset + 4 + signature_len)
+			.ok_or(format!("Quote signature data is truncated, expected {} bytes", signature_len))?
+			.to_vec();
+
+		Ok(SgxQuote { header, report_body, signature_data })
+	}
+}
+
+#[doc = "The TD report body of a TDX quote"]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TdReportBody {
+	pub tee_tcb_svn: [u8; 16],
+	pub mr_seam: [u8; 48],
+	pub mr_signer_seam: [u8; 48],
+	pub seam_attributes: u64,
+	pub td_attributes: u64,
+	pub xfam: u64,
+	pub mr_td: [u8; 48],
+	pub mr_config_id: [u8; 48],
+	pub mr_owner: [u8; 48],
+	pub mr_owner_config: [u8; 48],
+	pub rtmrs: [[u8; 48]; 4],
+	pub report_data: [u8; 64],
+}
+
+// In TDX, the DEBUG flag is bit 0 of TDATTRIBUTES.
+const TDX_ATTRIBUTES_DEBUG: u64 = 0x1;
+
+impl TdReportBody {
+	pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() < TD_REPORT_BODY_SIZE {
+			return Err(format!("TD report body is too short: {} bytes", bytes.len()))
+		}
+		Ok(TdReportBody {
+			tee_tcb_svn: array(bytes, 0),
+			mr_seam: array(bytes, 16),
+			mr_signer_seam: array(bytes, 64),
+			seam_attributes: u64_at(bytes, 112),
+			td_attributes: u64_at(bytes, 120),
+			xfam: u64_at(bytes, 128),
+			mr_td: array(bytes, 136),
+			mr_config_id: array(bytes, 184),
+			mr_owner: array(bytes, 232),
+			mr_owner_config: array(bytes, 280),
+			rtmrs: [array(bytes, 328), array(bytes, 376), array(bytes, 424), array(bytes, 472)],
+			report_data: array(bytes, 520),
+		})
+	}
+
+	pub fn is_debuggable(&self) -> bool {
+		self.td_attributes & TDX_ATTRIBUTES_DEBUG != 0
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TdxQuote {
+	pub header: QuoteHeader,
+	pub report_body: TdReportBody,
+	pub signature_data: Vec<u8>,
+}
+
+impl TdxQuote {
+	/// Parse a version 4 DCAP quote of a TDX trust domain.
+	pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+		let header = QuoteHeader::parse(bytes)?;
+		if header.version != 4 || header.tee_type != TEE_TYPE_TDX {
+			return Err(format!(
+				"Not a TDX quote, version {} tee type {:#x}",
+				header.version, header.tee_type
+			))
+		}
+
+		let report_body = TdReportBody::parse(&bytes[QUOTE_HEADER_SIZE..])?;
+
+		let offset = QUOTE_HEADER_SIZE + TD_REPORT_BODY_SIZE;
+		if bytes.len() < offset + 4 {
+			return Err("Quote has no signature data".into())
+		}
+		let signature_len = u32_at(bytes, offset) as usize;
+		let signature_data = bytes
+			.get(offset + 4..offset + 4 + signature_len)
+			.ok_or(format!("Quote signature data is truncated, expected {} bytes", signature_len))?
+			.to_vec();
+
+		Ok(TdxQuote { header, report_body, signature_data })
+	}
+}
+
+pub const OE_REPORT_HEADER_SIZE: usize = 16;
+
+// `oe_report_type_t` of a remote report, whose payload is an SGX quote.
+pub const OE_REPORT_TYPE_SGX_REMOTE: u32 = 2;
+
+/// Parse an OpenEnclave remote report: an `oe_report_header_t` followed by an SGX quote.
+pub fn parse_open_enclave_report(bytes: &[u8]) -> Result<SgxQuote, String> {
+	if bytes.len() < OE_REPORT_HEADER_SIZE {
+		return Err(format!("OpenEnclave report is too short: {} bytes", bytes.len()))
+	}
+	let version = u32_at(bytes, 0);
+	let report_type = u32_at(bytes, 4);
+	let report_size = u64_at(bytes, 8);
+	if version != 1 || report_type != OE_REPORT_TYPE_SGX_REMOTE {
+		return Err(format!(
+			"Unsupported OpenEnclave report, version {} type {}",
+			version, report_type
+		))
+	}
+
+	// The size is untrusted: it may not even fit the address space.
+	let quote = usize::try_from(report_size)
+		.ok()
+		.and_then(|size| OE_REPORT_HEADER_SIZE.checked_add(size))
+		.and_then(|end| bytes.get(OE_REPORT_HEADER_SIZE..end))
+		.ok_or_else(|| {
+			format!("OpenEnclave report is truncated, expected {} bytes", report_size)
+		})?;
+	SgxQuote::parse(quote)
+}
@@ -0,0 +1,148 @@
+//! A compact, SCALE encoded summary of a verified SGX attestation, for submission on chain.
+
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use codec::{Decode, Encode};
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+
+use crate::{
+	encoding::{base64url_decode, jws_header},
+	result::AttestationResult,
+};
+
+#[doc = "The attested properties of an SGX enclave and the token MAA issued for them"]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct AttestationReport {
+	pub mr_enclave: [u8; 32],
+	pub mr_signer: [u8; 32],
+	pub svn: u32,
+	pub product_id: u32,
+	pub is_debuggable: bool,
+	/// SHA-256 of the enclave held data, when the enclave sent any.
+	pub ehd_hash: Option<[u8; 32]>,
+	/// The token `iat`, in seconds since the Unix epoch.
+	pub issued_at: u64,
+	/// The `kid` of the provider key that signed the token.
+	pub key_id: Vec<u8>,
+	/// The token itself, so that the signature can be checked again.
+	pub token: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+	kid: Option<String>,
+}
+
+fn measurement(name: &str, value: Option<&str>) -> Result<[u8; 32], String> {
+	let value = value.ok_or_else(|| format!("Token has no {} claim", name))?;
+	let bytes = hex::decode(value).map_err(|e| format!("Invalid {} claim: {}", name, e))?;
+	bytes.try_into().map_err(|_| format!("Invalid {} claim: not 32 bytes", name))
+}
+
+impl AttestationReport {
+	/// The report of `token`, whose claims `result` are. `result` must be the outcome of verifying
+	/// `token`: nothing is verified here.
+	pub fn from_verified(token: &str, result: &AttestationResult) -> Result<Self, String> {
+		let header: Header =
+			serde_json::from_slice(&jws_header(token)?).map_err(|e| e.to_string())?;
+		let key_id = header.kid.ok_or("Token header has no kid")?;
+		let policy = &result.x_ms_policy;
+
+		let ehd = result.x_ms_sgx_ehd.as_deref().or(result.maa_ehd.as_deref());
+		let ehd_hash = match ehd {
+			Some(ehd) => {
+				let mut hash = [0u8; 32];
+				hash.copy_from_slice(digest(&SHA256, &base64url_decode(ehd)?).as_ref());
+				Some(hash)
+			},
+			None => None,
+		};
+
+		Ok(AttestationReport {
+			mr_enclave: measurement("sgx-mrenclave", policy.sgx_mrenclave.as_deref())?,
+			mr_signer: measurement("sgx-mrsigner", policy.sgx_mrsigner.as_deref())?,
+			svn: policy.svn.ok_or("Token has no svn claim")?,
+			product_id: policy.product_id.ok_or("Token has no product-id claim")?,
+			is_debuggable: policy.is_debuggable.ok_or("Token has no is-debuggable claim")?,
+			ehd_hash,
+			issued_at: result.iat.ok_or("Token has no iat claim")?,
+			key_id: key_id.into_bytes(),
+			token: token.trim().as_bytes().to_vec(),
+		})
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::encoding::jws_payload;
+
+	const RESPONSE: &str = include_str!("../../doc/token.json");
+
+	fn sample() -> (String, AttestationResult) {
+		let response: serde_json::Value = serde_json::from_str(RESPONSE).unwrap();
+		let token = response["token"].as_str().unwrap().to_string();
+		let result = serde_json::from_slice(&jws_payload(&token).unwrap()).unwrap();
+		(token, result)
+	}
+
+	#[test]
+	fn report_round_trips() {
+		let (token, result) = sample();
+		let report = AttestationReport::from_verified(&token, &result).unwrap();
+		assert_eq!(
+			hex::encode(report.mr_enclave),
+			"d37d983a85d63fb49649610e2eba0930ecdbff6d113aca3ff3fc7261696c0134"
+		);
+		assert_eq!((report.svn, report.product_id, report.is_debuggable), (8888, 1, false));
+		assert_eq!(report.issued_at, 1706152865);
+		assert_eq!(report.key_id, b"rFl9xM+g7TvX63y0iseZtIn20MD5SYAnGblKFasau8I=");
+		let ehd_hash = digest(&SHA256, &[1, 2, 3, 4, 5, 6]);
+		assert_eq!(report.ehd_hash.as_ref().map(|h| &h[..]), Some(ehd_hash.as_ref()));
+
+		let encoded = report.encode();
+		assert_eq!(encoded, report.encode());
+		assert_eq!(AttestationReport::decode(&mut &encoded[..]).unwrap(), report);
+		assert!(AttestationReport::decode(&mut &encoded[..encoded.len() - 1]).is_err());
+	}
+
+	#[test]
+	fn report_encoding_is_stable() {
+		let report = AttestationReport {
+			mr_enclave: [0xAA; 32],
+			mr_signer: [0xBB; 32],
+			svn: 1,
+			product_id: 2,
+			is_debuggable: false,
+			ehd_hash: None,
+			issued_at: 3,
+			key_id: b"k".to_vec(),
+			token: b"a.b.c".to_vec(),
+		};
+		let expected = [
+			&[0xAA; 32][..],
+			&[0xBB; 32],
+			&[1, 0, 0, 0],
+			&[2, 0, 0, 0],
+			&[0],
+			&[0],
+			&[3, 0, 0, 0, 0, 0, 0, 0],
+			&[4, b'k'],
+			&[20, b'a', b'.', b'b', b'.', b'c'],
+		]
+		.concat();
+		assert_eq!(report.encode(), expected);
+		assert_eq!(AttestationReport::decode(&mut &expected[..]).unwrap(), report);
+
+		let (token, mut result) = sample();
+		result.x_ms_policy.sgx_mrsigner = Some("feb995".into());
+		assert!(AttestationReport::from_verified(&token, &result).is_err());
+		result.x_ms_policy.sgx_mrsigner = None;
+		let err = AttestationReport::from_verified(&token, &result).unwrap_err();
+		assert_eq!(err, "Token has no sgx-mrsigner claim");
+	}
+}
@@ -0,0 +1,60 @@
+use alloc::{format, string::String};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::maa::MAAPolicy;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct AttestationResult {
+	#[serde(rename = "x-ms-policy")]
+	#[serde(flatten)]
+	pub x_ms_policy: MAAPolicy,
+
+	#[serde(rename = "x-ms-sgx-ehd", default, skip_serializing_if = "Option::is_none")]
+	pub x_ms_sgx_ehd: Option<String>,
+
+	#[serde(rename = "maa-ehd", default, skip_serializing_if = "Option::is_none")]
+	pub maa_ehd: Option<String>,
+
+	#[serde(rename = "x-ms-policy-hash", default, skip_serializing_if = "Option::is_none")]
+	pub x_ms_policy_hash: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub iss: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub iat: Option<u64>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub nbf: Option<u64>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub exp: Option<u64>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub nonce: Option<String>,
+
+	#[serde(rename = "x-ms-runtime", default, skip_serializing_if = "Option::is_none")]
+	pub x_ms_runtime: Option<Value>,
+
+	#[serde(rename = "x-ms-inittime", default, skip_serializing_if = "Option::is_none")]
+	pub x_ms_inittime: Option<Value>,
+
+	/// Every claim of the token, including those without a typed field above.
+	#[serde(skip)]
+	pub claims: Map<String, Value>,
+}
+
+impl AttestationResult {
+	/// The JSON runtime data the attestation was made with, from the `x-ms-runtime` claim.
+	pub fn runtime<T: DeserializeOwned>(&self) -> Result<T, String> {
+		let runtime = self.x_ms_runtime.clone().ok_or("Token has no x-ms-runtime claim")?;
+		serde_json::from_value(runtime).map_err(|e| format!("Invalid x-ms-runtime claim: {}", e))
+	}
+
+	/// The JSON init-time data of the TEE, from the `x-ms-inittime` claim.
+	pub fn inittime<T: DeserializeOwned>(&self) -> Result<T, String> {
+		let inittime = self.x_ms_inittime.clone().ok_or("Token has no x-ms-inittime claim")?;
+		serde_json::from_value(inittime).map_err(|e| format!("Invalid x-ms-inittime claim: {}", e))
+	}
+}
@@ -0,0 +1,99 @@
+//! Parsing of AMD SEV-SNP attestation reports, `ATTESTATION_REPORT` of the SEV-SNP firmware ABI.
+
+use alloc::{format, string::String, vec::Vec};
+
+pub const SNP_REPORT_SIZE: usize = 0x4A0;
+
+// Guest policy bit allowing the guest to be debugged.
+const SNP_POLICY_DEBUG: u64 = 1 << 19;
+
+// Guest policy bit allowing simultaneous multithreading.
+const SNP_POLICY_SMT: u64 = 1 << 16;
+
+// Guest policy bit allowing association with a migration agent.
+const SNP_POLICY_MIGRATE_MA: u64 = 1 << 18;
+
+#[doc = "The attestation report of an SEV-SNP guest"]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnpReport {
+	pub version: u32,
+	pub guest_svn: u32,
+	pub policy: u64,
+	pub family_id: [u8; 16],
+	pub image_id: [u8; 16],
+	pub vmpl: u32,
+	pub signature_algo: u32,
+	pub current_tcb: u64,
+	pub platform_info: u64,
+	pub report_data: [u8; 64],
+	pub measurement: [u8; 48],
+	pub host_data: [u8; 32],
+	pub id_key_digest: [u8; 48],
+	pub author_key_digest: [u8; 48],
+	pub report_id: [u8; 32],
+	pub report_id_ma: [u8; 32],
+	pub reported_tcb: u64,
+	pub chip_id: [u8; 64],
+	/// ECDSA P-384 signature over the first 0x2A0 bytes of the report.
+	pub signature: Vec<u8>,
+}
+
+fn array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+	let mut array = [0u8; N];
+	array.copy_from_slice(&bytes[offset..offset + N]);
+	array
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(array(bytes, offset))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+	u64::from_le_bytes(array(bytes, offset))
+}
+
+impl SnpReport {
+	pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() < SNP_REPORT_SIZE {
+			return Err(format!("SEV-SNP report is too short: {} bytes", bytes.len()))
+		}
+		let version = u32_at(bytes, 0x00);
+		if version < 2 {
+			return Err(format!("Unsupported SEV-SNP report version {}", version))
+		}
+
+		Ok(SnpReport {
+			version,
+			guest_svn: u32_at(bytes, 0x04),
+			policy: u64_at(bytes, 0x08),
+			family_id: array(bytes, 0x10),
+			image_id: array(bytes, 0x20),
+			vmpl: u32_at(bytes, 0x30),
+			signature_algo: u32_at(bytes, 0x34),
+			current_tcb: u64_at(bytes, 0x38),
+			platform_info: u64_at(bytes, 0x40),
+			report_data: array(bytes, 0x50),
+			measurement: array(bytes, 0x90),
+			host_data: array(bytes, 0xC0),
+			id_key_digest: array(bytes, 0xE0),
+			author_key_digest: array(bytes, 0x110),
+			report_id: array(bytes, 0x140),
+			report_id_ma: array(bytes, 0x160),
+			reported_tcb: u64_at(bytes, 0x180),
+			chip_id: array(bytes, 0x1A0),
+			signature: bytes[0x2A0..SNP_REPORT_SIZE].to_vec(),
+		})
+	}
+
+	pub fn is_debuggable(&self) -> bool {
+		self.policy & SNP_POLICY_DEBUG != 0
+	}
+
+	pub fn smt_allowed(&self) -> bool {
+		self.policy & SNP_POLICY_SMT != 0
+	}
+
+	pub fn migration_allowed(&self) -> bool {
+		self.policy & SNP_POLICY_MIGRATE_MA != 0
+	}
+}
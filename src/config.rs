@@ -1,11 +1,15 @@
 use crate::utils::read_string_from_file;
 use serde::{Deserialize, Serialize};
-use std::default::Default;
+use std::{default::Default, fs, str::FromStr};
+use url::Url;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
 	pub token: String,
 	pub endpoint: String,
+	/// Providers to fail over to, in order, when `endpoint` is unavailable.
+	#[serde(default, rename = "failoverEndpoints", skip_serializing_if = "Vec::is_empty")]
+	pub failover_endpoints: Vec<String>,
 }
 
 impl Config {
@@ -14,6 +18,22 @@ impl Config {
 		let info: Config = serde_json::from_str(&contents).expect("Failed to parse JSON");
 		info
 	}
+
+	pub fn from_file(path: &str) -> Result<Config, String> {
+		let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+		serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn endpoint_url(&self) -> Result<Url, String> {
+		Url::from_str(&self.endpoint).map_err(|e| format!("{}: {}", self.endpoint, e))
+	}
+
+	pub fn failover_urls(&self) -> Result<Vec<Url>, String> {
+		self.failover_endpoints
+			.iter()
+			.map(|endpoint| Url::from_str(endpoint).map_err(|e| format!("{}: {}", endpoint, e)))
+			.collect()
+	}
 }
 
 impl Default for Config {
@@ -0,0 +1,186 @@
+//! Binding of enclave held data to the quote: the enclave puts SHA-256 of the runtime data it
+//! sends along with the quote into the first 32 bytes of its REPORTDATA.
+//!
+//! Init-time data is bound by the host when it launches the TEE instead: SHA-256 of it is the
+//! lower 32 bytes of the SGX CONFIGID, or the SEV-SNP HOST_DATA.
+
+use std::fmt;
+
+use ring::digest::{digest, SHA256};
+
+use super::{quote::SgxQuote, snp::SnpReport};
+use crate::utils::{base64url, base64url_decode, AttestationResult};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BindingError {
+	/// SHA-256 of the runtime data is not at the start of the quote's REPORTDATA.
+	ReportData { expected: [u8; 32], actual: [u8; 32] },
+	/// The token's `x-ms-sgx-ehd` (or legacy `maa-ehd`) claim is missing or is not the runtime
+	/// data.
+	Claim { expected: String, actual: Option<String> },
+	/// SHA-256 of the init-time data is not the lower 32 bytes of the quote's CONFIGID.
+	ConfigId { expected: [u8; 32], actual: [u8; 32] },
+	/// SHA-256 of the init-time data is not the report's HOST_DATA.
+	HostData { expected: [u8; 32], actual: [u8; 32] },
+}
+
+impl fmt::Display for BindingError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BindingError::ReportData { expected, actual } => write!(
+				f,
+				"Quote REPORTDATA does not bind the runtime data: expected {}, found {}",
+				hex::encode(expected),
+				hex::encode(actual)
+			),
+			BindingError::Claim { expected, actual: Some(actual) } => write!(
+				f,
+				"Token enclave held data does not match the runtime data: expected {}, found {}",
+				expected, actual
+			),
+			BindingError::Claim { expected, actual: None } =>
+				write!(f, "Token has no enclave held data claim, expected {}", expected),
+			BindingError::ConfigId { expected, actual } => write!(
+				f,
+				"Quote CONFIGID does not bind the init-time data: expected {}, found {}",
+				hex::encode(expected),
+				hex::encode(actual)
+			),
+			BindingError::HostData { expected, actual } => write!(
+				f,
+				"Report HOST_DATA does not bind the init-time data: expected {}, found {}",
+				hex::encode(expected),
+				hex::encode(actual)
+			),
+		}
+	}
+}
+
+/// The enclave held data claim of the token, `x-ms-sgx-ehd` or else the legacy `maa-ehd`.
+pub fn ehd_claim(result: &AttestationResult) -> Option<&str> {
+	result.x_ms_sgx_ehd.as_deref().or(result.maa_ehd.as_deref())
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+	let mut hash = [0u8; 32];
+	hash.copy_from_slice(digest(&SHA256, data).as_ref());
+	hash
+}
+
+/// Check that SHA-256 of `runtime_data` is bound in the REPORTDATA of `quote`.
+pub fn verify_report_data(runtime_data: &[u8], quote: &SgxQuote) -> Result<(), BindingError> {
+	let expected = sha256(runtime_data);
+	let mut actual = [0u8; 32];
+	actual.copy_from_slice(&quote.report_body.report_data[..32]);
+
+	if expected != actual {
+		return Err(BindingError::ReportData { expected, actual })
+	}
+	Ok(())
+}
+
+/// Check that the token's enclave held data claim is `runtime_data`.
+pub fn verify_claim(runtime_data: &[u8], result: &AttestationResult) -> Result<(), BindingError> {
+	let actual = ehd_claim(result);
+	// The claim is base64url, older tokens used padded base64: compare the decoded bytes.
+	let matches = actual
+		.and_then(|claim| base64url_decode(claim).ok())
+		.is_some_and(|claim| claim == runtime_data);
+
+	if !matches {
+		return Err(BindingError::Claim {
+			expected: base64url(runtime_data),
+			actual: actual.map(str::to_string),
+		})
+	}
+	Ok(())
+}
+
+/// Check that SHA-256 of `init_time_data` is bound in the CONFIGID of `quote`.
+pub fn verify_config_id(init_time_data: &[u8], quote: &SgxQuote) -> Result<(), BindingError> {
+	let expected = sha256(init_time_data);
+	let mut actual = [0u8; 32];
+	actual.copy_from_slice(&quote.report_body.config_id[..32]);
+
+	if expected != actual {
+		return Err(BindingError::ConfigId { expected, actual })
+	}
+	Ok(())
+}
+
+/// Check that SHA-256 of `init_time_data` is the HOST_DATA of `report`.
+pub fn verify_host_data(init_time_data: &[u8], report: &SnpReport) -> Result<(), BindingError> {
+	let expected = sha256(init_time_data);
+	if expected != report.host_data {
+		return Err(BindingError::HostData { expected, actual: report.host_data })
+	}
+	Ok(())
+}
+
+/// Check both that `quote` binds `runtime_data`, and that the token MAA issued for the quote
+/// carries it. Every check that failed is reported.
+pub fn verify(
+	runtime_data: &[u8],
+	quote: &SgxQuote,
+	result: &AttestationResult,
+) -> Result<(), Vec<BindingError>> {
+	let errors: Vec<BindingError> =
+		[verify_report_data(runtime_data, quote), verify_claim(runtime_data, result)]
+			.into_iter()
+			.filter_map(Result::err)
+			.collect();
+
+	if !errors.is_empty() {
+		return Err(errors)
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::enclaves::{model::InitTimeData, snp::SNP_REPORT_SIZE};
+
+	const SGX_QUOTE: &str = include_str!("../../quotes/sgx_enclave_quote.txt");
+	const SGX_EHD: &str = include_str!("../../quotes/sgx_enclave_ehd.txt");
+
+	#[test]
+	fn ehd_binding_works() {
+		let quote = SgxQuote::parse(&hex::decode(SGX_QUOTE.trim()).unwrap()).unwrap();
+		let ehd = hex::decode(SGX_EHD.trim()).unwrap();
+
+		let result =
+			AttestationResult { x_ms_sgx_ehd: Some(base64url(&ehd)), ..Default::default() };
+		assert_eq!(verify(&ehd, &quote, &result), Ok(()));
+
+		let legacy = AttestationResult { maa_ehd: Some("AQIDBAUG".into()), ..Default::default() };
+		assert_eq!(verify(&ehd, &quote, &legacy), Ok(()));
+
+		let errors = verify(b"tampered", &quote, &result).unwrap_err();
+		assert!(matches!(
+			errors[..],
+			[BindingError::ReportData { .. }, BindingError::Claim { .. }]
+		));
+
+		let errors = verify(&ehd, &quote, &AttestationResult::default()).unwrap_err();
+		assert_eq!(errors, [BindingError::Claim { expected: "AQIDBAUG".into(), actual: None }]);
+	}
+
+	#[test]
+	fn init_time_data_binding_works() {
+		let init_time_data = InitTimeData::json(&serde_json::json!({ "disk": "sealed" })).unwrap();
+		let data = init_time_data.bytes().unwrap();
+
+		let mut quote = SgxQuote::parse(&hex::decode(SGX_QUOTE.trim()).unwrap()).unwrap();
+		assert!(matches!(verify_config_id(&data, &quote), Err(BindingError::ConfigId { .. })));
+		quote.report_body.config_id[..32].copy_from_slice(&init_time_data.hash().unwrap());
+		assert_eq!(verify_config_id(&data, &quote), Ok(()));
+
+		let mut report = vec![0u8; SNP_REPORT_SIZE];
+		report[0] = 2;
+		report[0xC0..0xE0].copy_from_slice(&init_time_data.hash().unwrap());
+		let report = SnpReport::parse(&report).unwrap();
+		assert_eq!(verify_host_data(&data, &report), Ok(()));
+		assert!(verify_host_data(b"other", &report).is_err());
+	}
+}
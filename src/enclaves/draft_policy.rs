@@ -0,0 +1,60 @@
+use crate::{
+	enclaves::{evidence::Evidence, model::RuntimeData},
+	service::{client::attestation, nonce},
+	utils::ClaimChange,
+};
+
+/// Attest the SGX quote `evidence`, with `runtime_data`, under the current policy and under the
+/// draft `policy`, and return how the claims issued under the draft differ.
+pub fn diff(
+	client: &attestation::Client,
+	evidence: &Evidence,
+	runtime_data: Option<RuntimeData>,
+	policy: &str,
+) -> Result<Vec<ClaimChange>, String> {
+	let mut request = evidence.sgx_request();
+	request.runtime_data = runtime_data;
+	// The same nonce for both, as the provider echoes it in the claims.
+	request.nonce = Some(nonce::generate()?);
+
+	let current = client.attest_sgx_enclave(request.clone()).into_result()?;
+	let draft = client
+		.attest_sgx_enclave(request)
+		.with_draft_policy(policy.to_string())
+		.into_result()?;
+	Ok(draft.diff(&current.claims))
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::{
+		enclaves::model::DataType,
+		service::{client::ClientBuilder, stand_in::SigningStandIn},
+		utils::base64,
+	};
+
+	const SGX_QUOTE: &[u8] = include_bytes!("../../quotes/sgx_enclave_quote.txt");
+	const SGX_EHD: &str = include_str!("../../quotes/sgx_enclave_ehd.txt");
+
+	#[test]
+	fn identical_policies_issue_the_same_claims() {
+		let provider = SigningStandIn::serve();
+		provider.attest_with(serde_json::json!({ "svn": 8888, "is-debuggable": false }));
+		let client = ClientBuilder::new("token".into(), provider.url())
+			.build()
+			.unwrap()
+			.attestation_client();
+
+		let evidence = Evidence::from_bytes(SGX_QUOTE).unwrap();
+		let ehd = base64(hex::decode(SGX_EHD.trim()).unwrap());
+		let runtime_data = Some(RuntimeData::new(ehd, DataType::Binary));
+		let changes = diff(&client, &evidence, runtime_data, "version=1.0;").unwrap();
+		assert!(changes.is_empty(), "{:?}", changes);
+
+		let requests = provider.stand_in.requests();
+		let attested: Vec<_> = requests.iter().filter(|request| request.method == "POST").collect();
+		assert_eq!(attested.len(), 2);
+		assert!(attested[1].body.contains("draftPolicyForAttestation"));
+	}
+}
@@ -1,7 +1,9 @@
 // use azure_svc_attestation::models::AttestationResult;
 use serde::{Deserialize, Serialize};
+use std::fs;
 
-use crate::utils::{base64, read_string_from_file, AttestationResult};
+use super::{binding, quote::SgxQuote};
+use crate::utils::{base64url, read_string_from_file, AttestationResult};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EnclaveInfo {
@@ -30,12 +32,60 @@ pub struct EnclaveInfo {
 	pub enclave_held_data_hex: String,
 }
 
+/// An expected property of the enclave, compared with what MAA attested.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Check {
+	pub name: &'static str,
+	pub expected: String,
+	pub actual: String,
+	pub passed: bool,
+}
+
+impl Check {
+	fn new(name: &'static str, expected: String, actual: String) -> Self {
+		let passed = expected.eq_ignore_ascii_case(&actual);
+		Check { name, expected, actual, passed }
+	}
+}
+
+/// The claim `value` as compared in checks, `missing` when the token has no such claim.
+pub(crate) fn claim<T: ToString>(value: &Option<T>) -> String {
+	value.as_ref().map_or_else(|| "missing".to_string(), T::to_string)
+}
+
 impl EnclaveInfo {
 	pub fn create_from_file(path: &str) -> EnclaveInfo {
 		let contents = read_string_from_file(path);
 		let info: EnclaveInfo = serde_json::from_str(&contents).expect("Failed to parse JSON");
 		info
 	}
+
+	pub fn from_file(path: &str) -> Result<EnclaveInfo, String> {
+		let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+		serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	/// Compare the enclave with the attestation result, without printing anything.
+	pub fn checks(&self, attest_result: &AttestationResult) -> Vec<Check> {
+		let policy = &attest_result.x_ms_policy;
+		let is_debuggable = (self.attributes & 2) != 0;
+
+		let mut checks = vec![
+			Check::new("is-debuggable", is_debuggable.to_string(), claim(&policy.is_debuggable)),
+			Check::new("mrenclave", self.mrenclave_hex.clone(), claim(&policy.sgx_mrenclave)),
+			Check::new("mrsigner", self.mrsigner_hex.clone(), claim(&policy.sgx_mrsigner)),
+			Check::new("svn", self.security_version.to_string(), claim(&policy.svn)),
+		];
+
+		let ehd = hex::decode(&self.enclave_held_data_hex).unwrap_or_default();
+		checks.push(Check {
+			name: "enclave-held-data",
+			expected: base64url(&ehd),
+			actual: binding::ehd_claim(attest_result).unwrap_or_default().to_string(),
+			passed: binding::verify_claim(&ehd, attest_result).is_ok(),
+		});
+		checks
+	}
 }
 
 pub trait ShowTime {
@@ -45,33 +95,28 @@ pub trait ShowTime {
 impl ShowTime for EnclaveInfo {
 	fn show_attest(&self, attest_result: &AttestationResult, include_details: bool) {
 		let is_debuggable = (self.attributes & 2) != 0; // In SGX, DEBUG flag is equal to 0x0000000000000002ULL
-		let isdpassed = is_debuggable == attest_result.x_ms_policy.is_debuggable;
+		let policy = &attest_result.x_ms_policy;
+		let isdpassed = Some(is_debuggable) == policy.is_debuggable;
 		println!("IsDebuggable match                 : {isdpassed}");
 		if include_details {
 			println!("    We think   : {is_debuggable}");
-			println!("    MAA service: {}", attest_result.x_ms_policy.is_debuggable);
+			println!("    MAA service: {}", claim(&policy.is_debuggable));
 		}
 
-		let mrepassed = self.mrenclave_hex ==
-			attest_result.x_ms_policy.sgx_mrenclave.clone().to_ascii_uppercase();
+		let mrenclave = claim(&policy.sgx_mrenclave).to_ascii_uppercase();
+		let mrepassed = policy.sgx_mrenclave.is_some() && self.mrenclave_hex == mrenclave;
 		println!("MRENCLAVE match                    : {mrepassed}");
 		if include_details {
 			println!("    We think   : {}", self.mrenclave_hex);
-			println!(
-				"    MAA service: {}",
-				attest_result.x_ms_policy.sgx_mrenclave.clone().to_ascii_uppercase()
-			);
+			println!("    MAA service: {}", mrenclave);
 		}
 
-		let mrspassed = self.mrsigner_hex ==
-			attest_result.x_ms_policy.sgx_mrsigner.clone().to_ascii_uppercase();
+		let mrsigner = claim(&policy.sgx_mrsigner).to_ascii_uppercase();
+		let mrspassed = policy.sgx_mrsigner.is_some() && self.mrsigner_hex == mrsigner;
 		println!("MRSIGNER match                     : {mrspassed}");
 		if include_details {
 			println!("    We think   : {}", self.mrsigner_hex);
-			println!(
-				"    MAA service: {}",
-				attest_result.x_ms_policy.sgx_mrsigner.clone().to_ascii_uppercase()
-			);
+			println!("    MAA service: {}", mrsigner);
 		}
 
 		// let product_id = u64::from_str_radix(&self.product_id_hex, 16).unwrap() as f64;
@@ -83,24 +128,59 @@ impl ShowTime for EnclaveInfo {
 		//     println!("    MAA service: {}", attest_result.product_id.unwrap());
 		// }
 
-		let svn_passed = self.security_version == attest_result.x_ms_policy.svn as f64;
+		let svn_passed = policy.svn.map(f64::from) == Some(self.security_version);
 		println!("Security Version match             : {svn_passed}");
 		if include_details {
 			println!("    We think   : {}", self.security_version);
-			println!("    MAA service: {}", attest_result.x_ms_policy.svn.to_string());
+			println!("    MAA service: {}", claim(&policy.svn));
 		}
 
-		let ehd_expected = hex::decode(&self.enclave_held_data_hex).unwrap();
-		let ehd_expected = base64(ehd_expected);
-
-		let ehd_actual = attest_result.x_ms_sgx_ehd.clone().unwrap();
-		let ehd_passed = ehd_expected == ehd_actual;
-		println!("Enclave Held Data match            : {ehd_passed}");
+		// The enclave info is user input: report what is wrong with it rather than panic.
+		let ehd = hex::decode(&self.enclave_held_data_hex)
+			.map_err(|e| format!("Invalid EnclaveHeldDataHex: {}", e));
+		let quote = hex::decode(&self.quote_hex)
+			.map_err(|e| format!("Invalid QuoteHex: {}", e))
+			.and_then(|quote| SgxQuote::parse(&quote));
+
+		let report_data = match (&ehd, &quote) {
+			(Ok(ehd), Ok(quote)) =>
+				binding::verify_report_data(ehd, quote).map_err(|e| e.to_string()),
+			(Err(err), _) | (_, Err(err)) => Err(err.clone()),
+		};
+		match report_data {
+			Ok(()) => println!("Enclave Held Data bound in quote   : true"),
+			Err(err) => println!("Enclave Held Data bound in quote   : false ({})", err),
+		}
+		let claim = match &ehd {
+			Ok(ehd) => binding::verify_claim(ehd, attest_result).is_ok(),
+			Err(_) => false,
+		};
+		println!("Enclave Held Data match            : {}", claim);
 		if include_details {
-			println!("    We think   :  {}", ehd_expected);
-			println!("    MAA service:  {}", ehd_actual);
+			match &ehd {
+				Ok(ehd) => println!("    We think   :  {}", base64url(ehd)),
+				Err(err) => println!("    We think   :  {}", err),
+			}
+			println!("    MAA service:  {}", binding::ehd_claim(attest_result).unwrap_or_default());
 		}
 
 		println!("");
 	}
 }
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	const ENCLAVE_INFO: &str = include_str!("../../quotes/enclave.info.securityversion.json");
+
+	#[test]
+	fn malformed_enclave_info_is_reported() {
+		let mut info: EnclaveInfo = serde_json::from_str(ENCLAVE_INFO).unwrap();
+		info.quote_hex.truncate(100);
+		info.show_attest(&AttestationResult::default(), true);
+		info.enclave_held_data_hex = "zz".into();
+		info.quote_hex = "0".into();
+		info.show_attest(&AttestationResult::default(), true);
+	}
+}
@@ -0,0 +1,191 @@
+//! Evidence as it is kept in files: hex or base64 text, raw binary, or a whole attest request.
+
+use std::{fmt, fs, path::Path};
+
+use azure_core::base64;
+use serde::Deserialize;
+
+use super::{
+	inspect::ParsedEvidence,
+	model::{
+		AttestOpenEnclaveRequest, AttestSevSnpVmRequest, AttestSgxEnclaveRequest, InitTimeData,
+		RuntimeData,
+	},
+};
+use crate::utils::{base64url, base64url_decode};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+	Hex,
+	Base64,
+	Base64Url,
+	Binary,
+	/// An attest request JSON, `{"quote": ..., "runtimeData": ...}`.
+	RequestJson,
+}
+
+impl fmt::Display for Encoding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Encoding::Hex => "hex",
+			Encoding::Base64 => "base64",
+			Encoding::Base64Url => "base64url",
+			Encoding::Binary => "binary",
+			Encoding::RequestJson => "request JSON",
+		})
+	}
+}
+
+/// Detect how `bytes` are encoded and decode them. Surrounding whitespace of text encodings is
+/// ignored, anything that is not valid hex or base64 is taken as raw binary.
+pub fn decode(bytes: &[u8]) -> (Encoding, Vec<u8>) {
+	let text = match std::str::from_utf8(bytes) {
+		Ok(text) => text.trim(),
+		Err(_) => return (Encoding::Binary, bytes.to_vec()),
+	};
+	if text.is_empty() {
+		return (Encoding::Binary, Vec::new())
+	}
+
+	if text.len() % 2 == 0 && text.bytes().all(|b| b.is_ascii_hexdigit()) {
+		if let Ok(data) = hex::decode(text) {
+			return (Encoding::Hex, data)
+		}
+	}
+	let is_base64 = |extra: &[u8]| {
+		text.trim_end_matches('=')
+			.bytes()
+			.all(|b| b.is_ascii_alphanumeric() || extra.contains(&b))
+	};
+	if is_base64(b"+/") {
+		if let Ok(data) = base64::decode(text) {
+			return (Encoding::Base64, data)
+		}
+	}
+	if is_base64(b"-_") {
+		if let Ok(data) = base64url_decode(text) {
+			return (Encoding::Base64Url, data)
+		}
+	}
+	(Encoding::Binary, bytes.to_vec())
+}
+
+/// Read the file at `path` and decode it, see `decode`.
+pub fn read(path: impl AsRef<Path>) -> Result<(Encoding, Vec<u8>), String> {
+	let path = path.as_ref();
+	let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+	Ok(decode(&bytes))
+}
+
+// The evidence members of an attest request of any TEE.
+#[derive(Deserialize)]
+struct EvidenceRequest {
+	#[serde(alias = "report")]
+	quote: String,
+	#[serde(rename = "runtimeData", default)]
+	runtime_data: Option<RuntimeData>,
+	#[serde(rename = "initTimeData", default)]
+	init_time_data: Option<InitTimeData>,
+}
+
+/// Decoded evidence, with the runtime and init-time data of a request JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evidence {
+	pub encoding: Encoding,
+	/// The quote or report.
+	pub quote: Vec<u8>,
+	pub runtime_data: Option<RuntimeData>,
+	pub init_time_data: Option<InitTimeData>,
+}
+
+impl Evidence {
+	/// Load evidence from a hex, base64, base64url, binary or attest request JSON file.
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+		Self::from_bytes(&bytes)
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if let Ok(request) = serde_json::from_slice::<EvidenceRequest>(bytes) {
+			let quote = base64url_decode(request.quote.trim())
+				.map_err(|e| format!("Invalid quote in request JSON: {}", e))?;
+			return Ok(Evidence {
+				encoding: Encoding::RequestJson,
+				quote,
+				runtime_data: request.runtime_data,
+				init_time_data: request.init_time_data,
+			})
+		}
+
+		let (encoding, quote) = decode(bytes);
+		if quote.is_empty() {
+			return Err("Evidence is empty".into())
+		}
+		Ok(Evidence { encoding, quote, runtime_data: None, init_time_data: None })
+	}
+
+	/// Detect the type of the evidence and parse it.
+	pub fn parse(&self) -> Result<ParsedEvidence, String> {
+		ParsedEvidence::detect(&self.quote)
+	}
+
+	pub fn sgx_request(&self) -> AttestSgxEnclaveRequest {
+		let mut request = AttestSgxEnclaveRequest::new();
+		request.quote = Some(base64::encode(&self.quote));
+		request.runtime_data = self.runtime_data.clone();
+		request.init_time_data = self.init_time_data.clone();
+		request
+	}
+
+	pub fn open_enclave_request(&self) -> AttestOpenEnclaveRequest {
+		let mut request = AttestOpenEnclaveRequest::new();
+		request.report = Some(base64::encode(&self.quote));
+		request.runtime_data = self.runtime_data.clone();
+		request.init_time_data = self.init_time_data.clone();
+		request
+	}
+
+	pub fn sev_snp_request(&self) -> AttestSevSnpVmRequest {
+		let mut request = AttestSevSnpVmRequest::new();
+		request.report = Some(base64url(&self.quote));
+		request.runtime_data = self.runtime_data.clone();
+		request.init_time_data = self.init_time_data.clone();
+		request
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::enclaves::model::DataType;
+
+	const SGX_QUOTE: &[u8] = include_bytes!("../../quotes/sgx_enclave_quote.txt");
+
+	#[test]
+	fn evidence_encodings_are_detected() {
+		let hex = Evidence::from_bytes(SGX_QUOTE).unwrap();
+		assert_eq!(hex.encoding, Encoding::Hex);
+		assert!(matches!(hex.parse(), Ok(ParsedEvidence::Sgx(_))));
+
+		let padded = format!("\n{}\r\n", base64::encode(&hex.quote));
+		let base64 = Evidence::from_bytes(padded.as_bytes()).unwrap();
+		assert_eq!((base64.encoding, &base64.quote), (Encoding::Base64, &hex.quote));
+
+		let base64url = Evidence::from_bytes(base64url(&hex.quote).as_bytes()).unwrap();
+		assert_eq!(base64url.encoding, Encoding::Base64Url);
+		assert_eq!(base64url.quote, hex.quote);
+
+		let request = serde_json::json!({
+			"quote": super::base64url(&hex.quote),
+			"runtimeData": { "data": "AQIDBAUG", "dataType": "Binary" },
+		});
+		let request = Evidence::from_bytes(request.to_string().as_bytes()).unwrap();
+		assert_eq!(request.encoding, Encoding::RequestJson);
+		assert_eq!(request.quote, hex.quote);
+		assert_eq!(request.sgx_request().runtime_data.unwrap().data_type, DataType::Binary);
+
+		assert_eq!(Evidence::from_bytes(&[0xff, 0x00]).unwrap().encoding, Encoding::Binary);
+		assert!(Evidence::from_bytes(b" \n").is_err());
+	}
+}
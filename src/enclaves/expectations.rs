@@ -0,0 +1,267 @@
+//! Expectations documents: which attested enclaves to accept, as sets of builds rather than the
+//! single enclave of an `EnclaveInfo`, e.g. during a rollout.
+//!
+//! ```yaml
+//! tee: sgx
+//! allow_debug: false
+//! mrenclaves:
+//!   - D37D983A85D63FB49649610E2EBA0930ECDBFF6D113ACA3FF3FC7261696C0134
+//! signers:
+//!   - mrsigner: FEB995EB86C349AC98E5AFBBB5732CA7376EC9979002702EA17AD476E0853A04
+//!     product_id: 1
+//!     min_svn: 8888
+//! ```
+//!
+//! An enclave is accepted when its MRENCLAVE is listed, or it meets any of the signer rules. With
+//! neither listed, any enclave of the TEE type is.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::enclave_info::{claim, Check};
+use crate::utils::AttestationResult;
+
+/// An expectations document, in YAML or JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Expectations {
+	/// The required `x-ms-attestation-type`, e.g. `sgx` or `sevsnpvm`.
+	#[serde(default)]
+	pub tee: Option<String>,
+
+	/// Whether debuggable enclaves are accepted. They are not by default.
+	#[serde(default)]
+	pub allow_debug: bool,
+
+	/// Accepted enclave builds, hex encoded.
+	#[serde(default)]
+	pub mrenclaves: Vec<String>,
+
+	/// Accepted signers, each with a minimum security version.
+	#[serde(default)]
+	pub signers: Vec<SignerRule>,
+}
+
+/// Enclaves signed by `mrsigner`, of the product `product_id` if given, at security version
+/// `min_svn` or later.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SignerRule {
+	pub mrsigner: String,
+
+	#[serde(default)]
+	pub product_id: Option<u32>,
+
+	#[serde(default)]
+	pub min_svn: u32,
+}
+
+impl Expectations {
+	/// Parse an expectations document, JSON when it starts with `{`, YAML otherwise.
+	pub fn parse(text: &str) -> Result<Expectations, String> {
+		if text.trim_start().starts_with('{') {
+			serde_json::from_str(text).map_err(|e| format!("Invalid expectations: {}", e))
+		} else {
+			serde_yaml::from_str(text).map_err(|e| format!("Invalid expectations: {}", e))
+		}
+	}
+
+	pub fn from_file(path: &str) -> Result<Expectations, String> {
+		let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+		Self::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+	}
+}
+
+/// Evaluates attestation results against an `Expectations`, checked once up front.
+#[derive(Debug, Clone)]
+pub struct ExpectationMatcher {
+	expectations: Expectations,
+}
+
+impl ExpectationMatcher {
+	pub fn new(mut expectations: Expectations) -> Result<Self, String> {
+		for measurement in expectations
+			.mrenclaves
+			.iter_mut()
+			.chain(expectations.signers.iter_mut().map(|rule| &mut rule.mrsigner))
+		{
+			let valid = measurement.len() == 64 && hex::decode(&*measurement).is_ok();
+			if !valid {
+				return Err(format!("Invalid measurement {}: not 32 hex encoded bytes", measurement))
+			}
+			measurement.make_ascii_uppercase();
+		}
+		Ok(Self { expectations })
+	}
+
+	pub fn from_file(path: &str) -> Result<Self, String> {
+		Self::new(Expectations::from_file(path)?).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn expectations(&self) -> &Expectations {
+		&self.expectations
+	}
+
+	/// Whether the attested enclave meets every expectation.
+	pub fn matches(&self, attest_result: &AttestationResult) -> bool {
+		self.checks(attest_result).iter().all(|check| check.passed)
+	}
+
+	/// Compare the expectations with the attestation result, one check per expectation set.
+	pub fn checks(&self, attest_result: &AttestationResult) -> Vec<Check> {
+		let expectations = &self.expectations;
+		let policy = &attest_result.x_ms_policy;
+		let tee = match attest_result.claims.get("x-ms-attestation-type") {
+			Some(Value::String(tee)) => tee.clone(),
+			_ => claim(&policy.tee),
+		};
+		let mut checks = Vec::new();
+
+		if let Some(expected) = &expectations.tee {
+			checks.push(Check {
+				name: "tee",
+				expected: expected.clone(),
+				actual: tee.clone(),
+				passed: expected.eq_ignore_ascii_case(&tee),
+			});
+		}
+
+		let is_debuggable = match attest_result.claims.get("x-ms-sevsnpvm-is-debuggable") {
+			Some(Value::Bool(is_debuggable)) => Some(*is_debuggable),
+			_ => policy.is_debuggable,
+		};
+		if !expectations.allow_debug {
+			checks.push(Check {
+				name: "is-debuggable",
+				expected: false.to_string(),
+				actual: claim(&is_debuggable),
+				passed: is_debuggable == Some(false),
+			});
+		}
+
+		if !expectations.mrenclaves.is_empty() || !expectations.signers.is_empty() {
+			let mrenclave = claim(&policy.sgx_mrenclave).to_ascii_uppercase();
+			let mrsigner = claim(&policy.sgx_mrsigner).to_ascii_uppercase();
+			let listed = expectations.mrenclaves.contains(&mrenclave);
+			let signed = expectations.signers.iter().any(|rule| {
+				rule.mrsigner == mrsigner &&
+					rule.product_id.map_or(true, |id| Some(id) == policy.product_id) &&
+					policy.svn.is_some_and(|svn| svn >= rule.min_svn)
+			});
+			checks.push(Check {
+				name: "identity",
+				expected: self.identities(),
+				actual: format!(
+					"mrenclave {}, mrsigner {}, product-id {}, svn {}",
+					mrenclave,
+					mrsigner,
+					claim(&policy.product_id),
+					claim(&policy.svn)
+				),
+				passed: listed || signed,
+			});
+		}
+		checks
+	}
+
+	// The accepted enclaves, as reported in the identity check.
+	fn identities(&self) -> String {
+		let mut identities: Vec<String> = self
+			.expectations
+			.mrenclaves
+			.iter()
+			.map(|mrenclave| format!("mrenclave {}", mrenclave))
+			.collect();
+		identities.extend(self.expectations.signers.iter().map(|rule| {
+			let product = rule.product_id.map(|id| format!(", product-id {}", id));
+			format!(
+				"mrsigner {}{}, svn >= {}",
+				rule.mrsigner,
+				product.unwrap_or_default(),
+				rule.min_svn
+			)
+		}));
+		identities.join(" or ")
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	const MRENCLAVE: &str = "d37d983a85d63fb49649610e2eba0930ecdbff6d113aca3ff3fc7261696c0134";
+	const MRSIGNER: &str = "feb995eb86c349ac98e5afbbb5732ca7376ec9979002702ea17ad476e0853a04";
+
+	fn attested(claims: Value) -> AttestationResult {
+		let mut result: AttestationResult = serde_json::from_value(claims.clone()).unwrap();
+		result.claims = claims.as_object().unwrap().clone();
+		result
+	}
+
+	fn sgx(mrenclave: &str, svn: u32, is_debuggable: bool) -> AttestationResult {
+		attested(serde_json::json!({
+			"x-ms-attestation-type": "sgx",
+			"sgx-mrenclave": mrenclave,
+			"sgx-mrsigner": MRSIGNER,
+			"product-id": 1,
+			"svn": svn,
+			"is-debuggable": is_debuggable,
+		}))
+	}
+
+	#[test]
+	fn enclave_builds_and_signers_are_matched() {
+		let yaml = format!(
+			"tee: sgx\nmrenclaves:\n  - {}\nsigners:\n  - mrsigner: {}\n    product_id: 1\n    \
+			 min_svn: 8888\n",
+			"AB".repeat(32),
+			MRSIGNER
+		);
+		let matcher = ExpectationMatcher::new(Expectations::parse(&yaml).unwrap()).unwrap();
+		assert_eq!(matcher.expectations().signers[0].mrsigner, MRSIGNER.to_ascii_uppercase());
+
+		// A listed build, or a signer rule met at its minimum SVN or later.
+		assert!(matcher.matches(&sgx(&"ab".repeat(32), 1, false)));
+		assert!(matcher.matches(&sgx(MRENCLAVE, 8888, false)));
+		assert!(matcher.matches(&sgx(MRENCLAVE, 9000, false)));
+
+		let checks = matcher.checks(&sgx(MRENCLAVE, 8887, false));
+		let identity = checks.iter().find(|check| check.name == "identity").unwrap();
+		assert!(!identity.passed);
+		assert!(identity.expected.contains("svn >= 8888"), "{}", identity.expected);
+		assert!(identity.actual.ends_with("svn 8887"), "{}", identity.actual);
+
+		let debug = matcher.checks(&sgx(MRENCLAVE, 8888, true));
+		assert_eq!(debug.iter().filter(|check| !check.passed).count(), 1);
+		assert_eq!(debug[1].name, "is-debuggable");
+
+		let snp = attested(serde_json::json!({
+			"x-ms-attestation-type": "sevsnpvm",
+			"x-ms-sevsnpvm-is-debuggable": false,
+		}));
+		let checks = matcher.checks(&snp);
+		assert_eq!((checks[0].name, checks[0].passed), ("tee", false));
+		assert!(checks[1].passed);
+	}
+
+	#[test]
+	fn documents_are_validated() {
+		let json = format!(r#"{{ "allow_debug": true, "mrenclaves": ["{}"] }}"#, MRENCLAVE);
+		let matcher = ExpectationMatcher::new(Expectations::parse(&json).unwrap()).unwrap();
+		let checks = matcher.checks(&sgx(MRENCLAVE, 0, true));
+		assert_eq!(checks.len(), 1);
+		assert!(checks[0].passed);
+
+		// Anything of the TEE type, debuggable or not, with nothing listed.
+		let any = ExpectationMatcher::new(Expectations::default()).unwrap();
+		assert!(any.matches(&sgx(MRENCLAVE, 0, false)));
+		assert!(!any.matches(&sgx(MRENCLAVE, 0, true)));
+
+		assert!(Expectations::parse("signers:\n  - mrsigner: AB\n    min-svn: 1\n").is_err());
+		let short = Expectations::parse("mrenclaves: [ABCD]").unwrap();
+		let err = ExpectationMatcher::new(short).unwrap_err();
+		assert!(err.contains("ABCD"), "{}", err);
+	}
+}
@@ -0,0 +1,212 @@
+//! Detection of the type of evidence, and its fields for display.
+
+use serde_json::{Map, Value};
+
+use super::{
+	quote::{
+		parse_open_enclave_report, QuoteHeader, ReportBody, SgxQuote, TdxQuote,
+		OE_REPORT_TYPE_SGX_REMOTE, TEE_TYPE_SGX, TEE_TYPE_TDX,
+	},
+	snp::{SnpReport, SNP_REPORT_SIZE},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsedEvidence {
+	Sgx(SgxQuote),
+	Tdx(TdxQuote),
+	OpenEnclave(SgxQuote),
+	SevSnp(SnpReport),
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+impl ParsedEvidence {
+	/// Detect the type of the decoded evidence `bytes` and parse it.
+	pub fn detect(bytes: &[u8]) -> Result<Self, String> {
+		// An OpenEnclave report header: version 1, then the report type.
+		if u32_at(bytes, 0) == Some(1) && u32_at(bytes, 4) == Some(OE_REPORT_TYPE_SGX_REMOTE) {
+			return parse_open_enclave_report(bytes).map(ParsedEvidence::OpenEnclave)
+		}
+		// SNP reports start with a 32-bit version, where quotes have a 16-bit version followed
+		// by a non-zero attestation key type.
+		if bytes.len() >= SNP_REPORT_SIZE && u16_at(bytes, 2) == Some(0) {
+			return SnpReport::parse(bytes).map(ParsedEvidence::SevSnp)
+		}
+
+		let header = QuoteHeader::parse(bytes)?;
+		match (header.version, header.tee_type) {
+			(3, _) | (4, TEE_TYPE_SGX) => SgxQuote::parse(bytes).map(ParsedEvidence::Sgx),
+			(4, TEE_TYPE_TDX) => TdxQuote::parse(bytes).map(ParsedEvidence::Tdx),
+			(version, tee_type) =>
+				Err(format!("Unknown evidence: quote version {} tee type {:#x}", version, tee_type)),
+		}
+	}
+
+	pub fn kind(&self) -> &'static str {
+		match self {
+			ParsedEvidence::Sgx(quote) if quote.header.version == 3 => "SGX quote v3",
+			ParsedEvidence::Sgx(_) => "SGX quote v4",
+			ParsedEvidence::Tdx(_) => "TDX quote v4",
+			ParsedEvidence::OpenEnclave(_) => "OpenEnclave report",
+			ParsedEvidence::SevSnp(_) => "SEV-SNP report",
+		}
+	}
+
+	/// Every parsed field, in layout order, binary fields hex encoded.
+	pub fn fields(&self) -> Vec<(&'static str, String)> {
+		match self {
+			ParsedEvidence::Sgx(quote) | ParsedEvidence::OpenEnclave(quote) => {
+				let mut fields = header_fields(&quote.header);
+				fields.extend(report_body_fields(&quote.report_body));
+				fields.push(("signature_data_len", quote.signature_data.len().to_string()));
+				fields
+			},
+			ParsedEvidence::Tdx(quote) => {
+				let body = &quote.report_body;
+				let mut fields = header_fields(&quote.header);
+				fields.extend([
+					("tee_tcb_svn", hex::encode(body.tee_tcb_svn)),
+					("mr_seam", hex::encode(body.mr_seam)),
+					("mr_signer_seam", hex::encode(body.mr_signer_seam)),
+					("seam_attributes", format!("{:#018x}", body.seam_attributes)),
+					("td_attributes", format!("{:#018x}", body.td_attributes)),
+					("is_debuggable", body.is_debuggable().to_string()),
+					("xfam", format!("{:#018x}", body.xfam)),
+					("mr_td", hex::encode(body.mr_td)),
+					("mr_config_id", hex::encode(body.mr_config_id)),
+					("mr_owner", hex::encode(body.mr_owner)),
+					("mr_owner_config", hex::encode(body.mr_owner_config)),
+					("rtmr0", hex::encode(body.rtmrs[0])),
+					("rtmr1", hex::encode(body.rtmrs[1])),
+					("rtmr2", hex::encode(body.rtmrs[2])),
+					("rtmr3", hex::encode(body.rtmrs[3])),
+					("report_data", hex::encode(body.report_data)),
+					("signature_data_len", quote.signature_data.len().to_string()),
+				]);
+				fields
+			},
+			ParsedEvidence::SevSnp(report) => vec![
+				("version", report.version.to_string()),
+				("guest_svn", report.guest_svn.to_string()),
+				("policy", format!("{:#018x}", report.policy)),
+				("is_debuggable", report.is_debuggable().to_string()),
+				("smt_allowed", report.smt_allowed().to_string()),
+				("migration_allowed", report.migration_allowed().to_string()),
+				("family_id", hex::encode(report.family_id)),
+				("image_id", hex::encode(report.image_id)),
+				("vmpl", report.vmpl.to_string()),
+				("signature_algo", report.signature_algo.to_string()),
+				("current_tcb", format!("{:#018x}", report.current_tcb)),
+				("platform_info", format!("{:#018x}", report.platform_info)),
+				("report_data", hex::encode(report.report_data)),
+				("measurement", hex::encode(report.measurement)),
+				("host_data", hex::encode(report.host_data)),
+				("id_key_digest", hex::encode(report.id_key_digest)),
+				("author_key_digest", hex::encode(report.author_key_digest)),
+				("report_id", hex::encode(report.report_id)),
+				("report_id_ma", hex::encode(report.report_id_ma)),
+				("reported_tcb", format!("{:#018x}", report.reported_tcb)),
+				("chip_id", hex::encode(report.chip_id)),
+			],
+		}
+	}
+
+	/// The type and fields as a JSON object.
+	pub fn to_json(&self) -> Value {
+		let fields: Map<String, Value> = self
+			.fields()
+			.into_iter()
+			.map(|(name, value)| (name.to_string(), Value::String(value)))
+			.collect();
+		serde_json::json!({ "type": self.kind(), "fields": fields })
+	}
+}
+
+fn header_fields(header: &QuoteHeader) -> Vec<(&'static str, String)> {
+	vec![
+		("version", header.version.to_string()),
+		("att_key_type", header.att_key_type.to_string()),
+		("tee_type", format!("{:#x}", header.tee_type)),
+		("qe_svn", header.qe_svn.to_string()),
+		("pce_svn", header.pce_svn.to_string()),
+		("qe_vendor_id", hex::encode(header.qe_vendor_id)),
+		("user_data", hex::encode(header.user_data)),
+	]
+}
+
+fn report_body_fields(body: &ReportBody) -> Vec<(&'static str, String)> {
+	vec![
+		("cpu_svn", hex::encode(body.cpu_svn)),
+		("misc_select", format!("{:#010x}", body.misc_select)),
+		("isv_ext_prod_id", hex::encode(body.isv_ext_prod_id)),
+		("attributes.flags", format!("{:#018x}", body.attributes.flags)),
+		("attributes.xfrm", format!("{:#018x}", body.attributes.xfrm)),
+		("is_debuggable", body.is_debuggable().to_string()),
+		("mr_enclave", hex::encode(body.mr_enclave)),
+		("mr_signer", hex::encode(body.mr_signer)),
+		("config_id", hex::encode(body.config_id)),
+		("isv_prod_id", body.isv_prod_id.to_string()),
+		("isv_svn", body.isv_svn.to_string()),
+		("config_svn", body.config_svn.to_string()),
+		("isv_family_id", hex::encode(body.isv_family_id)),
+		("report_data", hex::encode(body.report_data)),
+	]
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::enclaves::{
+		evidence::{decode, Encoding},
+		quote::{QUOTE_HEADER_SIZE, TD_REPORT_BODY_SIZE},
+	};
+
+	const SGX_QUOTE: &[u8] = include_bytes!("../../quotes/sgx_enclave_quote.txt");
+	const SGX_QUOTE_DAT: &[u8] = include_bytes!("../../quotes/quote.dat");
+	const OE_REPORT: &[u8] = include_bytes!("../../quotes/open_enclave_quote.txt");
+
+	#[test]
+	fn sample_evidence_is_detected() {
+		let (encoding, hex_quote) = decode(SGX_QUOTE);
+		assert_eq!(encoding, Encoding::Hex);
+		let (encoding, binary_quote) = decode(SGX_QUOTE_DAT);
+		assert_eq!(encoding, Encoding::Binary);
+		assert!(matches!(ParsedEvidence::detect(&binary_quote), Ok(ParsedEvidence::Sgx(_))));
+
+		let evidence = ParsedEvidence::detect(&hex_quote).unwrap();
+		assert_eq!(evidence.kind(), "SGX quote v3");
+		assert_eq!(evidence.to_json()["fields"]["isv_svn"], "8888");
+
+		let mut report = decode(OE_REPORT).1;
+		let evidence = ParsedEvidence::detect(&report).unwrap();
+		assert_eq!(evidence.kind(), "OpenEnclave report");
+		report[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+		let err = ParsedEvidence::detect(&report).unwrap_err();
+		assert!(err.contains("truncated"), "{}", err);
+	}
+
+	#[test]
+	fn tdx_and_snp_are_detected() {
+		let mut quote = vec![0u8; QUOTE_HEADER_SIZE + TD_REPORT_BODY_SIZE + 4];
+		quote[0] = 4;
+		quote[2] = 2;
+		quote[4] = TEE_TYPE_TDX as u8;
+		quote[QUOTE_HEADER_SIZE + 136] = 0xab;
+		let evidence = ParsedEvidence::detect(&quote).unwrap();
+		assert_eq!(evidence.kind(), "TDX quote v4");
+		assert!(evidence.fields().contains(&("mr_td", format!("ab{}", "00".repeat(47)))));
+
+		let mut report = vec![0u8; SNP_REPORT_SIZE];
+		report[0] = 2;
+		report[0x0A] = 0x08;
+		let evidence = ParsedEvidence::detect(&report).unwrap();
+		assert_eq!(evidence.kind(), "SEV-SNP report");
+		assert!(evidence.fields().contains(&("is_debuggable", "true".into())));
+	}
+}
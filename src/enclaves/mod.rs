@@ -1,5 +1,11 @@
+pub mod binding;
+pub mod draft_policy;
 pub mod enclave_info;
-pub mod model;
+pub mod evidence;
+pub mod expectations;
+pub mod inspect;
 pub mod open_enclave;
 pub mod sgx_enclave;
 pub mod test_enclave;
+
+pub use azure_attest_core::{model, quote, snp};
@@ -1,9 +1,4 @@
-use crate::{
-	config::Config,
-	enclaves::model::AttestOpenEnclaveRequest,
-	service::client::ClientBuilder,
-	utils::{base64, read_string_from_file},
-};
+use crate::{config::Config, enclaves::evidence::Evidence, service::client::ClientBuilder};
 use std::str::FromStr;
 use url::Url;
 
@@ -15,13 +10,7 @@ pub fn verify() {
 		.unwrap()
 		.attestation_client();
 
-	let mut request = AttestOpenEnclaveRequest::new();
-
-	let report = read_string_from_file("quotes/open_enclave_quote.txt");
-	let report = hex::decode(report).unwrap();
-	let report = base64(report);
-
-	request.report = Some(report);
+	let request = Evidence::load("quotes/open_enclave_quote.txt").unwrap().open_enclave_request();
 
 	let request_builder = client.attest_open_enclave(request);
 	let response = request_builder.send().unwrap();
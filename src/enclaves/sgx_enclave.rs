@@ -4,11 +4,12 @@ use serde_json::Value;
 
 use super::{
 	enclave_info::{EnclaveInfo, ShowTime},
+	evidence::{self, Evidence},
 	model::{AttestationRequest, DataType, RuntimeData},
 };
 use crate::{
 	config::Config,
-	utils::{base64, decode_attest_result, read_string_from_file},
+	utils::{base64, decode_attest_result},
 	MAA,
 };
 
@@ -22,12 +23,10 @@ impl MAA for SgxEnclave {
 		let subscription_key = config.token;
 		let bearer_token = format!("Bearer {}", subscription_key);
 
-		let quote = read_string_from_file("quotes/sgx_enclave_quote.txt");
-		let quote = hex::decode(quote).unwrap();
-		let quote = base64(quote);
+		let quote = Evidence::load("quotes/sgx_enclave_quote.txt").unwrap();
+		let quote = base64(quote.quote);
 
-		let ehd = read_string_from_file("quotes/sgx_enclave_ehd.txt");
-		let ehd = hex::decode(ehd).unwrap();
+		let (_, ehd) = evidence::read("quotes/sgx_enclave_ehd.txt").unwrap();
 		let ehd = base64(ehd);
 
 		let runtime_data = RuntimeData::new(ehd, DataType::Binary);
@@ -7,14 +7,9 @@ use url::Url;
 
 use super::{
 	enclave_info::{EnclaveInfo, ShowTime},
-	model::AttestSgxEnclaveRequest,
-};
-use crate::{
-	config::Config,
-	service::client::ClientBuilder,
-	utils::{base64, read_string_from_file},
-	MAA,
+	evidence::Evidence,
 };
+use crate::{config::Config, service::client::ClientBuilder, MAA};
 
 pub struct TestEnclave;
 
@@ -26,12 +21,7 @@ impl MAA for TestEnclave {
 			.unwrap()
 			.attestation_client();
 
-		let quote = read_string_from_file("quotes/sgx_enclave_quote.txt");
-		let quote = hex::decode(quote).unwrap();
-		let quote = base64(quote);
-
-		let mut request = AttestSgxEnclaveRequest::new();
-		request.quote = Some(quote);
+		let request = Evidence::load("quotes/sgx_enclave_quote.txt").unwrap().sgx_request();
 
 		let request_builder = client.attest_sgx_enclave(request);
 		match request_builder.send() {
@@ -0,0 +1,256 @@
+//! Public keys an attested enclave ships in its JSON runtime data, and wrapping of secrets to
+//! them for secure key release.
+//!
+//! The enclave sends a JSON Web Key set as runtime data, `{"keys": [...]}`, so that MAA issues it
+//! in the `x-ms-runtime` claim. Once the token is verified, a secret wrapped to one of these keys
+//! can only be unwrapped inside the attested enclave.
+
+use ring::{
+	aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+	agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, ECDH_P256},
+	digest::{digest, SHA256},
+	rand::{SecureRandom, SystemRandom},
+};
+use rsa::{rand_core::OsRng, sha2::Sha256, BigUint, Oaep, RsaPublicKey};
+use serde_json::json;
+
+use crate::{
+	enclaves::model::{JsonWebKey, JsonWebKeySet},
+	utils::{base64url, base64url_decode, AttestationResult},
+};
+
+/// Content encryption of the wrapped secret.
+pub const CONTENT_ENCRYPTION: &str = "A256GCM";
+
+// Length of an A256GCM content encryption key.
+const CEK_LEN: usize = 32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnclaveKey {
+	/// Secrets are wrapped with RSA-OAEP-256.
+	Rsa { kid: Option<String>, key: RsaPublicKey },
+	/// Secrets are wrapped with ECDH-ES. `point` is the uncompressed P-256 point.
+	Ec { kid: Option<String>, point: Vec<u8> },
+}
+
+fn member<'a>(
+	jwk: &'a JsonWebKey,
+	name: &str,
+	value: &'a Option<String>,
+) -> Result<Vec<u8>, String> {
+	let value = value.as_ref().ok_or(format!("{} JWK has no {}", jwk.kty, name))?;
+	base64url_decode(value)
+}
+
+impl EnclaveKey {
+	/// The key of an RSA or P-256 EC `jwk`.
+	pub fn from_jwk(jwk: &JsonWebKey) -> Result<Self, String> {
+		let kid = jwk.kid.clone();
+		match jwk.kty.as_str() {
+			"RSA" => {
+				let n = BigUint::from_bytes_be(&member(jwk, "n", &jwk.n)?);
+				let e = BigUint::from_bytes_be(&member(jwk, "e", &jwk.e)?);
+				let key = RsaPublicKey::new(n, e).map_err(|e| e.to_string())?;
+				Ok(EnclaveKey::Rsa { kid, key })
+			},
+			"EC" => {
+				if jwk.crv.as_deref() != Some("P-256") {
+					return Err(format!("Unsupported EC JWK curve {:?}", jwk.crv))
+				}
+				let x = member(jwk, "x", &jwk.x)?;
+				let y = member(jwk, "y", &jwk.y)?;
+				if x.len() != 32 || y.len() != 32 {
+					return Err("Invalid P-256 JWK coordinates".into())
+				}
+				let point = [&[0x04][..], &x, &y].concat();
+				Ok(EnclaveKey::Ec { kid, point })
+			},
+			kty => Err(format!("Unsupported JWK key type {}", kty)),
+		}
+	}
+
+	pub fn kid(&self) -> Option<&str> {
+		match self {
+			EnclaveKey::Rsa { kid, .. } | EnclaveKey::Ec { kid, .. } => kid.as_deref(),
+		}
+	}
+
+	/// Wrap `secret` to this key, as a compact JWE encrypted with A256GCM.
+	pub fn wrap(&self, secret: &[u8]) -> Result<String, String> {
+		let rng = SystemRandom::new();
+		let (mut header, cek, encrypted_key) = match self {
+			EnclaveKey::Rsa { key, .. } => {
+				let mut cek = [0u8; CEK_LEN];
+				rng.fill(&mut cek).map_err(|_| "Failed to generate a content key".to_string())?;
+				let encrypted_key = key
+					.encrypt(&mut OsRng, Oaep::new::<Sha256>(), &cek)
+					.map_err(|e| e.to_string())?;
+				(json!({ "alg": "RSA-OAEP-256" }), cek, encrypted_key)
+			},
+			EnclaveKey::Ec { point, .. } => {
+				let (epk, cek) = ecdh_es(point, &rng)?;
+				(json!({ "alg": "ECDH-ES", "epk": epk }), cek, vec![])
+			},
+		};
+		header["enc"] = json!(CONTENT_ENCRYPTION);
+		if let Some(kid) = self.kid() {
+			header["kid"] = json!(kid);
+		}
+		let protected = base64url(header.to_string().as_bytes());
+
+		let mut iv = [0u8; NONCE_LEN];
+		rng.fill(&mut iv).map_err(|_| "Failed to generate an iv".to_string())?;
+		let key = UnboundKey::new(&AES_256_GCM, &cek).map_err(|_| "Invalid content key")?;
+		let mut ciphertext = secret.to_vec();
+		LessSafeKey::new(key)
+			.seal_in_place_append_tag(
+				Nonce::assume_unique_for_key(iv),
+				Aad::from(protected.as_bytes()),
+				&mut ciphertext,
+			)
+			.map_err(|_| "Failed to encrypt the secret".to_string())?;
+		let tag = ciphertext.split_off(ciphertext.len() - AES_256_GCM.tag_len());
+
+		Ok(format!(
+			"{}.{}.{}.{}.{}",
+			protected,
+			base64url(&encrypted_key),
+			base64url(&iv),
+			base64url(&ciphertext),
+			base64url(&tag)
+		))
+	}
+}
+
+// The ephemeral public key and the content key agreed with `point`, using the Concat KDF of
+// RFC 7518 section 4.6.2 with empty party infos.
+fn ecdh_es(point: &[u8], rng: &SystemRandom) -> Result<(serde_json::Value, [u8; CEK_LEN]), String> {
+	let ephemeral = EphemeralPrivateKey::generate(&ECDH_P256, rng)
+		.map_err(|_| "Failed to generate an ephemeral key".to_string())?;
+	let public = ephemeral.compute_public_key().map_err(|_| "Invalid ephemeral key")?;
+	let public = public.as_ref();
+	let epk = json!({
+		"kty": "EC",
+		"crv": "P-256",
+		"x": base64url(&public[1..33]),
+		"y": base64url(&public[33..65]),
+	});
+
+	let peer = UnparsedPublicKey::new(&ECDH_P256, point);
+	let cek = agreement::agree_ephemeral(ephemeral, &peer, (), concat_kdf)
+		.map_err(|_| "Key agreement with the enclave key failed".to_string())?;
+	Ok((epk, cek))
+}
+
+pub(crate) fn concat_kdf(z: &[u8]) -> Result<[u8; CEK_LEN], ()> {
+	let algorithm = CONTENT_ENCRYPTION.as_bytes();
+	let mut input = 1u32.to_be_bytes().to_vec();
+	input.extend_from_slice(z);
+	input.extend_from_slice(&(algorithm.len() as u32).to_be_bytes());
+	input.extend_from_slice(algorithm);
+	input.extend_from_slice(&0u32.to_be_bytes());
+	input.extend_from_slice(&0u32.to_be_bytes());
+	input.extend_from_slice(&((CEK_LEN * 8) as u32).to_be_bytes());
+
+	let mut cek = [0u8; CEK_LEN];
+	cek.copy_from_slice(digest(&SHA256, &input).as_ref());
+	Ok(cek)
+}
+
+/// The RSA and EC keys of the `x-ms-runtime.keys` JWK set in a verified token. Keys of other
+/// types are skipped.
+pub fn enclave_keys(result: &AttestationResult) -> Result<Vec<EnclaveKey>, String> {
+	let runtime = result.x_ms_runtime.as_ref().ok_or("Token has no x-ms-runtime claim")?;
+	let keys = runtime.get("keys").ok_or("x-ms-runtime claim has no keys")?;
+	let keys: JsonWebKeySet = serde_json::from_value(json!({ "keys": keys }))
+		.map_err(|e| format!("Invalid x-ms-runtime keys: {}", e))?;
+
+	keys.keys
+		.iter()
+		.filter(|jwk| jwk.kty == "RSA" || jwk.kty == "EC")
+		.map(EnclaveKey::from_jwk)
+		.collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use rsa::{traits::PublicKeyParts, RsaPrivateKey};
+
+	const SECRET: &[u8] = b"released to attested enclaves only";
+
+	fn open(jwe: &str, cek: &[u8]) -> Vec<u8> {
+		let parts: Vec<&str> = jwe.split('.').collect();
+		let iv = base64url_decode(parts[2]).unwrap();
+		let mut in_out =
+			[base64url_decode(parts[3]).unwrap(), base64url_decode(parts[4]).unwrap()].concat();
+
+		let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, cek).unwrap());
+		let nonce = Nonce::try_assume_unique_for_key(&iv).unwrap();
+		key.open_in_place(nonce, Aad::from(parts[0].as_bytes()), &mut in_out)
+			.unwrap()
+			.to_vec()
+	}
+
+	fn header(jwe: &str) -> serde_json::Value {
+		serde_json::from_slice(&base64url_decode(jwe.split('.').next().unwrap()).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn rsa_wrapped_secret_unwraps() {
+		let private = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+		let result = AttestationResult {
+			x_ms_runtime: Some(json!({ "keys": [
+				{ "kty": "oct", "kid": "skipped" },
+				{
+					"kty": "RSA",
+					"kid": "enclave",
+					"n": base64url(&private.n().to_bytes_be()),
+					"e": base64url(&private.e().to_bytes_be()),
+				},
+			]})),
+			..Default::default()
+		};
+
+		let keys = enclave_keys(&result).unwrap();
+		assert_eq!(keys.len(), 1);
+		assert_eq!(keys[0].kid(), Some("enclave"));
+
+		let jwe = keys[0].wrap(SECRET).unwrap();
+		assert_eq!(header(&jwe)["alg"], "RSA-OAEP-256");
+		let encrypted_key = base64url_decode(jwe.split('.').nth(1).unwrap()).unwrap();
+		let cek = private.decrypt(Oaep::new::<Sha256>(), &encrypted_key).unwrap();
+		assert_eq!(open(&jwe, &cek), SECRET);
+	}
+
+	#[test]
+	fn ec_wrapped_secret_unwraps() {
+		let rng = SystemRandom::new();
+		let private = EphemeralPrivateKey::generate(&ECDH_P256, &rng).unwrap();
+		let public = private.compute_public_key().unwrap();
+		let jwk = JsonWebKey {
+			kty: "EC".into(),
+			crv: Some("P-256".into()),
+			x: Some(base64url(&public.as_ref()[1..33])),
+			y: Some(base64url(&public.as_ref()[33..])),
+			..Default::default()
+		};
+
+		let jwe = EnclaveKey::from_jwk(&jwk).unwrap().wrap(SECRET).unwrap();
+		let epk = &header(&jwe)["epk"];
+		let epk = [
+			vec![0x04],
+			base64url_decode(epk["x"].as_str().unwrap()).unwrap(),
+			base64url_decode(epk["y"].as_str().unwrap()).unwrap(),
+		]
+		.concat();
+		let cek = agreement::agree_ephemeral(
+			private,
+			&UnparsedPublicKey::new(&ECDH_P256, epk),
+			(),
+			concat_kdf,
+		)
+		.unwrap();
+		assert_eq!(open(&jwe, &cek), SECRET);
+	}
+}
@@ -1,8 +1,18 @@
 mod config;
-mod service;
 mod utils;
+mod x509;
 
 pub mod enclaves;
+pub mod keys;
+pub mod policy;
+pub mod ra_tls;
+pub mod service;
+
+pub use azure_attest_core::AttestationReport;
+pub use config::Config;
+pub use utils::{
+	base64url, base64url_decode, AttestationResult, ClaimChange, DraftAttestationResult,
+};
 
 pub enum EnclaveType {
 	SgxEnclave,
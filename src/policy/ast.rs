@@ -0,0 +1,250 @@
+use std::fmt;
+
+/// A location in the policy text, both 1-based.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+	pub line: usize,
+	pub column: usize,
+}
+
+impl fmt::Display for Position {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.line, self.column)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Policy {
+	/// The declared policy language version, e.g. `1.0`.
+	pub version: String,
+	pub sections: Vec<Section>,
+}
+
+impl Policy {
+	pub fn section(&self, kind: SectionKind) -> Option<&Section> {
+		self.sections.iter().find(|section| section.kind == kind)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+	Configuration,
+	Authorization,
+	Issuance,
+}
+
+impl SectionKind {
+	pub fn keyword(&self) -> &'static str {
+		match self {
+			SectionKind::Configuration => "configurationrules",
+			SectionKind::Authorization => "authorizationrules",
+			SectionKind::Issuance => "issuancerules",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+	pub kind: SectionKind,
+	pub rules: Vec<Rule>,
+	pub position: Position,
+}
+
+/// `conditions => action;`, an empty condition list always matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+	pub conditions: Vec<Condition>,
+	pub action: Action,
+	pub position: Position,
+}
+
+/// `binding:[matcher, ...]`, matching any single claim that satisfies every matcher.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+	pub binding: Option<String>,
+	pub matchers: Vec<Matcher>,
+	pub position: Position,
+}
+
+impl Condition {
+	/// The claim type this condition requires, if it is matched by equality.
+	pub fn claim_type(&self) -> Option<&str> {
+		self.matchers.iter().find_map(|matcher| match matcher {
+			Matcher {
+				property: Property::Type,
+				operator: Operator::Equal,
+				value: Value::String(claim_type),
+				..
+			} => Some(claim_type.as_str()),
+			_ => None,
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matcher {
+	pub property: Property,
+	pub operator: Operator,
+	pub value: Value,
+	pub position: Position,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+	Type,
+	Value,
+	Issuer,
+}
+
+impl fmt::Display for Property {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Property::Type => "type",
+			Property::Value => "value",
+			Property::Issuer => "issuer",
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+	Equal,
+	NotEqual,
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+}
+
+impl fmt::Display for Operator {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Operator::Equal => "==",
+			Operator::NotEqual => "!=",
+			Operator::Less => "<",
+			Operator::LessOrEqual => "<=",
+			Operator::Greater => ">",
+			Operator::GreaterOrEqual => ">=",
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	String(String),
+	Integer(i64),
+	Boolean(bool),
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::String(s) => write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+			Value::Integer(i) => write!(f, "{}", i),
+			Value::Boolean(b) => write!(f, "{}", b),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+	Permit,
+	Deny,
+	/// Adds a claim to the token.
+	Issue(ClaimSpec),
+	/// Adds a claim to the incoming claims, for later rules to match on.
+	Add(ClaimSpec),
+	/// Sets a configuration property, only valid in `configurationrules`.
+	IssueProperty(ClaimSpec),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClaimSpec {
+	/// `claim=c`, the whole claim matched by a binding.
+	Claim(String),
+	/// `type="...", value=...`.
+	TypeValue { claim_type: String, value: Expr },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+	Literal(Value),
+	/// `c.type`, `c.value` or `c.issuer` of a bound claim.
+	Reference {
+		binding: String,
+		property: Property,
+	},
+}
+
+impl fmt::Display for Expr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Expr::Literal(value) => write!(f, "{}", value),
+			Expr::Reference { binding, property } => write!(f, "{}.{}", binding, property),
+		}
+	}
+}
+
+impl fmt::Display for ClaimSpec {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ClaimSpec::Claim(binding) => write!(f, "claim={}", binding),
+			ClaimSpec::TypeValue { claim_type, value } => {
+				write!(f, "type={}, value={}", Value::String(claim_type.clone()), value)
+			},
+		}
+	}
+}
+
+impl fmt::Display for Action {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Action::Permit => write!(f, "permit()"),
+			Action::Deny => write!(f, "deny()"),
+			Action::Issue(spec) => write!(f, "issue({})", spec),
+			Action::Add(spec) => write!(f, "add({})", spec),
+			Action::IssueProperty(spec) => write!(f, "issueproperty({})", spec),
+		}
+	}
+}
+
+impl fmt::Display for Condition {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(binding) = &self.binding {
+			write!(f, "{}:", binding)?;
+		}
+		let matchers: Vec<String> = self
+			.matchers
+			.iter()
+			.map(|m| format!("{}{}{}", m.property, m.operator, m.value))
+			.collect();
+		write!(f, "[{}]", matchers.join(", "))
+	}
+}
+
+impl fmt::Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let conditions: Vec<String> = self.conditions.iter().map(|c| c.to_string()).collect();
+		if conditions.is_empty() {
+			write!(f, "=> {};", self.action)
+		} else {
+			write!(f, "{} => {};", conditions.join(" && "), self.action)
+		}
+	}
+}
+
+/// The canonical form of the policy.
+impl fmt::Display for Policy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "version={};", self.version)?;
+		for section in &self.sections {
+			writeln!(f, "{}", section.kind.keyword())?;
+			writeln!(f, "{{")?;
+			for rule in &section.rules {
+				writeln!(f, "    {}", rule)?;
+			}
+			writeln!(f, "}};")?;
+		}
+		Ok(())
+	}
+}
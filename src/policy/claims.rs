@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+	enclaves::{
+		model::{AttestationType, DataType, RuntimeData},
+		quote::SgxQuote,
+	},
+	utils::base64url,
+};
+
+/// Issuer of the incoming claims MAA derives from the evidence.
+pub const SERVICE_ISSUER: &str = "AttestationService";
+
+/// Issuer of the claims added or issued by policy rules.
+pub const POLICY_ISSUER: &str = "AttestationPolicy";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+	#[serde(rename = "type")]
+	pub claim_type: String,
+	pub value: Value,
+	pub issuer: String,
+}
+
+impl Claim {
+	pub fn new(claim_type: impl Into<String>, value: Value) -> Self {
+		Self { claim_type: claim_type.into(), value, issuer: SERVICE_ISSUER.to_string() }
+	}
+}
+
+/// The incoming claims MAA presents to the policy for an SGX or OpenEnclave `quote`.
+pub fn sgx_claims(
+	quote: &SgxQuote,
+	tee: AttestationType,
+	runtime_data: Option<&RuntimeData>,
+) -> Result<Vec<Claim>, String> {
+	let body = &quote.report_body;
+	let attestation_type = match tee {
+		AttestationType::SgxEnclave => "sgx",
+		AttestationType::OpenEnclave => "openenclave",
+		tee => return Err(format!("Not an SGX attestation type: {}", tee)),
+	};
+
+	let mut claims = vec![
+		Claim::new("x-ms-attestation-type", json!(attestation_type)),
+		Claim::new("x-ms-sgx-is-debuggable", json!(body.is_debuggable())),
+		Claim::new("x-ms-sgx-product-id", json!(body.isv_prod_id)),
+		Claim::new("x-ms-sgx-mrsigner", json!(hex::encode(body.mr_signer))),
+		Claim::new("x-ms-sgx-mrenclave", json!(hex::encode(body.mr_enclave))),
+		Claim::new("x-ms-sgx-svn", json!(body.isv_svn)),
+		Claim::new("x-ms-sgx-config-id", json!(hex::encode(body.config_id))),
+		Claim::new("x-ms-sgx-config-svn", json!(body.config_svn)),
+		Claim::new("x-ms-sgx-isv-extended-product-id", json!(hex::encode(body.isv_ext_prod_id))),
+		Claim::new("x-ms-sgx-isv-family-id", json!(hex::encode(body.isv_family_id))),
+		Claim::new("x-ms-sgx-report-data", json!(hex::encode(body.report_data))),
+		// Policy version 1.0 names.
+		Claim::new("$is-debuggable", json!(body.is_debuggable())),
+		Claim::new("$sgx-mrsigner", json!(hex::encode(body.mr_signer))),
+		Claim::new("$sgx-mrenclave", json!(hex::encode(body.mr_enclave))),
+		Claim::new("$product-id", json!(body.isv_prod_id)),
+		Claim::new("$svn", json!(body.isv_svn)),
+		Claim::new("$tee", json!(attestation_type)),
+	];
+
+	if let Some(runtime_data) = runtime_data {
+		let data = runtime_data.bytes()?;
+		match runtime_data.data_type {
+			DataType::Binary => claims.push(Claim::new("x-ms-sgx-ehd", json!(base64url(&data)))),
+			DataType::Json => {
+				let runtime: Value = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+				claims.push(Claim::new("x-ms-runtime", runtime));
+			},
+		}
+	}
+
+	Ok(claims)
+}
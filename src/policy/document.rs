@@ -0,0 +1,194 @@
+use azure_core::base64;
+use ring::{
+	rand::SystemRandom,
+	signature::{EcdsaKeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, RSA_PKCS1_SHA256},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	enclaves::model::{AttestationPolicy, StoredAttestationPolicy},
+	service::token::{unsigned_jws, Jws, JwsHeader},
+	utils::{base64url, base64url_decode, AttestationResult},
+};
+
+/// A key used to sign policies and policy management requests, together with the certificate
+/// registered with the attestation provider for it.
+pub enum PolicySigningKey {
+	Rsa { key_pair: RsaKeyPair, certificate: Vec<u8> },
+	Ecdsa { key_pair: EcdsaKeyPair, certificate: Vec<u8> },
+}
+
+impl PolicySigningKey {
+	/// An RSA key, signing with RS256. `certificate` is DER encoded.
+	pub fn rsa_from_pkcs8(pkcs8: &[u8], certificate: Vec<u8>) -> Result<Self, String> {
+		let key_pair = RsaKeyPair::from_pkcs8(pkcs8).map_err(|e| e.to_string())?;
+		Ok(PolicySigningKey::Rsa { key_pair, certificate })
+	}
+
+	/// A P-256 key, signing with ES256. `certificate` is DER encoded.
+	pub fn ecdsa_from_pkcs8(pkcs8: &[u8], certificate: Vec<u8>) -> Result<Self, String> {
+		let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8)
+			.map_err(|e| e.to_string())?;
+		Ok(PolicySigningKey::Ecdsa { key_pair, certificate })
+	}
+
+	fn certificate(&self) -> &[u8] {
+		match self {
+			PolicySigningKey::Rsa { certificate, .. } |
+			PolicySigningKey::Ecdsa { certificate, .. } => certificate,
+		}
+	}
+
+	/// Wrap `payload` in a JWS signed with this key, carrying the certificate in `x5c`.
+	///
+	/// Also used for the bodies of policy certificate management and signed reset requests.
+	pub fn sign_jws(&self, payload: &[u8]) -> Result<String, String> {
+		let alg = match self {
+			PolicySigningKey::Rsa { .. } => "RS256",
+			PolicySigningKey::Ecdsa { .. } => "ES256",
+		};
+		let header = JwsHeader {
+			alg: alg.to_string(),
+			kid: None,
+			jku: None,
+			typ: Some("JWT".to_string()),
+			x5c: Some(vec![base64::encode(self.certificate())]),
+		};
+		let header = serde_json::to_vec(&header).map_err(|e| e.to_string())?;
+		let signing_input = format!("{}.{}", base64url(&header), base64url(payload));
+
+		let rng = SystemRandom::new();
+		let signature = match self {
+			PolicySigningKey::Rsa { key_pair, .. } => {
+				let mut signature = vec![0; key_pair.public_modulus_len()];
+				key_pair
+					.sign(&RSA_PKCS1_SHA256, &rng, signing_input.as_bytes(), &mut signature)
+					.map_err(|e| e.to_string())?;
+				signature
+			},
+			PolicySigningKey::Ecdsa { key_pair, .. } => key_pair
+				.sign(&rng, signing_input.as_bytes())
+				.map_err(|e| e.to_string())?
+				.as_ref()
+				.to_vec(),
+		};
+
+		Ok(format!("{}.{}", signing_input, base64url(&signature)))
+	}
+}
+
+/// The text of an attestation policy, and the tokens it is wrapped in when it is set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDocument {
+	text: String,
+}
+
+impl PolicyDocument {
+	pub fn new(text: impl Into<String>) -> Self {
+		Self { text: text.into() }
+	}
+
+	/// Extract the policy out of a policy token, signed or not.
+	pub fn from_token(token: &str) -> Result<Self, String> {
+		let jws = Jws::parse(token)?;
+		let stored: StoredAttestationPolicy =
+			serde_json::from_slice(&jws.claims).map_err(|e| e.to_string())?;
+		let text = stored.attestation_policy.ok_or("Policy token holds no policy")?;
+		let text = String::from_utf8(base64url_decode(&text)?).map_err(|e| e.to_string())?;
+		Ok(Self { text })
+	}
+
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	fn payload(&self) -> Result<Vec<u8>, String> {
+		let stored =
+			StoredAttestationPolicy { attestation_policy: Some(base64url(self.text.as_bytes())) };
+		serde_json::to_vec(&stored).map_err(|e| e.to_string())
+	}
+
+	/// The policy wrapped in an `alg: none` JWS, as accepted by AAD mode providers.
+	pub fn unsigned_token(&self) -> Result<String, String> {
+		Ok(unsigned_jws(&self.payload()?))
+	}
+
+	/// The policy wrapped in a JWS signed by `key`, as required by isolated mode providers.
+	pub fn signed_token(&self, key: &PolicySigningKey) -> Result<String, String> {
+		key.sign_jws(&self.payload()?)
+	}
+
+	/// The `x-ms-policy-hash` claim of tokens issued under this policy:
+	/// `BASE64URL(SHA256(UTF8(BASE64URL(UTF8(policy text)))))`.
+	pub fn policy_hash(&self) -> String {
+		let encoded = base64url(self.text.as_bytes());
+		base64url(ring::digest::digest(&ring::digest::SHA256, encoded.as_bytes()).as_ref())
+	}
+
+	/// Whether `attest_result` was issued under exactly this policy.
+	pub fn matches(&self, attest_result: &AttestationResult) -> bool {
+		attest_result.x_ms_policy_hash.as_deref() == Some(self.policy_hash().as_str())
+	}
+}
+
+impl From<PolicyDocument> for AttestationPolicy {
+	fn from(document: PolicyDocument) -> Self {
+		AttestationPolicy::Text(document.text)
+	}
+}
+
+/// The `x-ms-policy-token-hash` reported when setting `token`: `BASE64URL(SHA256(token))`.
+pub fn policy_token_hash(token: &str) -> String {
+	base64url(ring::digest::digest(&ring::digest::SHA256, token.as_bytes()).as_ref())
+}
+
+#[cfg(test)]
+pub mod tests {
+	use ring::signature::{KeyPair, UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+
+	use super::*;
+
+	const POLICY: &str = "version=1.0; authorizationrules{=> permit();};";
+
+	#[test]
+	fn policy_hash_works() {
+		let document = PolicyDocument::new(POLICY);
+		assert_eq!(document.policy_hash(), "pHPUQTy9GKln0SOxCk30Xtjoj_7tmdQCT2VxdvPIQaE");
+
+		let attest_result = AttestationResult {
+			x_ms_policy_hash: Some(document.policy_hash()),
+			..Default::default()
+		};
+		assert!(document.matches(&attest_result));
+		assert!(!PolicyDocument::new("version=1.0;").matches(&attest_result));
+	}
+
+	#[test]
+	fn unsigned_token_round_trips() {
+		let document = PolicyDocument::new(POLICY);
+		let token = document.unsigned_token().unwrap();
+		assert!(token.starts_with("eyJhbGciOiJub25lIn0."));
+		assert_eq!(PolicyDocument::from_token(&token).unwrap(), document);
+	}
+
+	#[test]
+	fn es256_signed_token_verifies() {
+		let rng = SystemRandom::new();
+		let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+		let key = PolicySigningKey::ecdsa_from_pkcs8(pkcs8.as_ref(), vec![0, 1, 2]).unwrap();
+		let PolicySigningKey::Ecdsa { key_pair, .. } = &key else { unreachable!() };
+		let public_key = key_pair.public_key().as_ref().to_vec();
+
+		let document = PolicyDocument::new(POLICY);
+		let token = document.signed_token(&key).unwrap();
+		let jws = Jws::parse(&token).unwrap();
+		assert_eq!(jws.header.alg, "ES256");
+		assert_eq!(jws.header.x5c, Some(vec!["AAEC".to_string()]));
+
+		let (signing_input, _) = token.rsplit_once('.').unwrap();
+		UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key)
+			.verify(signing_input.as_bytes(), &jws.signature)
+			.unwrap();
+		assert_eq!(PolicyDocument::from_token(&token).unwrap(), document);
+	}
+}
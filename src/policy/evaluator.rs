@@ -0,0 +1,338 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use serde_json::{Map, Value as Json};
+
+use super::{
+	ast::{
+		Action, ClaimSpec, Condition, Expr, Matcher, Operator, Policy, Property, SectionKind, Value,
+	},
+	claims::{Claim, POLICY_ISSUER},
+};
+
+/// The outcome of evaluating a policy against a set of incoming claims.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Evaluation {
+	pub permitted: bool,
+	/// Claims issued by `issuancerules`, in rule order. Empty when not permitted.
+	pub issued: Vec<Claim>,
+	/// Properties set by `configurationrules`.
+	pub properties: Vec<Claim>,
+}
+
+impl Evaluation {
+	/// The issued claims as token claims: repeated claim types become arrays.
+	pub fn claims(&self) -> Map<String, Json> {
+		let mut claims = Map::new();
+		for claim in &self.issued {
+			match claims.get_mut(&claim.claim_type) {
+				None => {
+					claims.insert(claim.claim_type.clone(), claim.value.clone());
+				},
+				Some(Json::Array(values)) => values.push(claim.value.clone()),
+				Some(existing) =>
+					*existing = Json::Array(vec![existing.clone(), claim.value.clone()]),
+			}
+		}
+		claims
+	}
+}
+
+fn compare(actual: &Json, operator: Operator, expected: &Value) -> bool {
+	let ordering = match (actual, expected) {
+		(Json::String(actual), Value::String(expected)) => actual.cmp(expected),
+		(Json::Number(actual), Value::Integer(expected)) => match actual.as_i64() {
+			Some(actual) => actual.cmp(expected),
+			None => return operator == Operator::NotEqual,
+		},
+		(Json::Bool(actual), Value::Boolean(expected)) => actual.cmp(expected),
+		_ => return operator == Operator::NotEqual,
+	};
+
+	match operator {
+		Operator::Equal => ordering == Ordering::Equal,
+		Operator::NotEqual => ordering != Ordering::Equal,
+		Operator::Less => ordering == Ordering::Less,
+		Operator::LessOrEqual => ordering != Ordering::Greater,
+		Operator::Greater => ordering == Ordering::Greater,
+		Operator::GreaterOrEqual => ordering != Ordering::Less,
+	}
+}
+
+fn property(claim: &Claim, property: Property) -> Json {
+	match property {
+		Property::Type => Json::String(claim.claim_type.clone()),
+		Property::Value => claim.value.clone(),
+		Property::Issuer => Json::String(claim.issuer.clone()),
+	}
+}
+
+fn matches(claim: &Claim, matcher: &Matcher) -> bool {
+	compare(&property(claim, matcher.property), matcher.operator, &matcher.value)
+}
+
+// Every way of picking one claim per condition, such that each picked claim satisfies its
+// condition. No conditions match exactly once.
+fn matching_claims<'a>(conditions: &[Condition], claims: &'a [Claim]) -> Vec<Vec<&'a Claim>> {
+	let mut combinations: Vec<Vec<&Claim>> = vec![vec![]];
+	for condition in conditions {
+		let candidates: Vec<&Claim> = claims
+			.iter()
+			.filter(|claim| condition.matchers.iter().all(|matcher| matches(claim, matcher)))
+			.collect();
+		combinations = combinations
+			.into_iter()
+			.flat_map(|picked| {
+				candidates.iter().map(move |candidate| {
+					let mut picked = picked.clone();
+					picked.push(*candidate);
+					picked
+				})
+			})
+			.collect();
+	}
+	combinations
+}
+
+fn materialize(
+	spec: &ClaimSpec,
+	conditions: &[Condition],
+	picked: &[&Claim],
+) -> Result<Claim, String> {
+	let bound: HashMap<&str, &Claim> = conditions
+		.iter()
+		.zip(picked)
+		.filter_map(|(condition, claim)| condition.binding.as_deref().map(|b| (b, *claim)))
+		.collect();
+	let lookup = |binding: &str| {
+		bound
+			.get(binding)
+			.copied()
+			.ok_or(format!("Reference to unbound claim `{}`", binding))
+	};
+
+	let (claim_type, value) = match spec {
+		ClaimSpec::Claim(binding) => {
+			let claim = lookup(binding)?;
+			(claim.claim_type.clone(), claim.value.clone())
+		},
+		ClaimSpec::TypeValue { claim_type, value: Expr::Literal(value) } => {
+			let value = match value {
+				Value::String(s) => Json::String(s.clone()),
+				Value::Integer(i) => Json::from(*i),
+				Value::Boolean(b) => Json::Bool(*b),
+			};
+			(claim_type.clone(), value)
+		},
+		ClaimSpec::TypeValue { claim_type, value: Expr::Reference { binding, property: p } } =>
+			(claim_type.clone(), property(lookup(binding)?, *p)),
+	};
+
+	Ok(Claim { claim_type, value, issuer: POLICY_ISSUER.to_string() })
+}
+
+/// Evaluate `policy` against the `incoming` claims, the way the service would.
+///
+/// `authorizationrules` are evaluated in order: `add()` extends the incoming claims, and the
+/// first matching `permit()` or `deny()` decides. A policy without authorization rules permits,
+/// one whose rules never decide denies. When permitted, every matching `issuancerules` rule is
+/// applied in order. The claims MAA always emits, whatever the policy, are not included.
+pub fn evaluate(policy: &Policy, incoming: Vec<Claim>) -> Result<Evaluation, String> {
+	let mut incoming = incoming;
+	let mut evaluation = Evaluation::default();
+
+	if let Some(section) = policy.section(SectionKind::Configuration) {
+		for rule in &section.rules {
+			let Action::IssueProperty(spec) = &rule.action else {
+				return Err(format!(
+					"{}: only issueproperty() is valid in configurationrules",
+					rule.position
+				))
+			};
+			for picked in matching_claims(&rule.conditions, &incoming) {
+				evaluation.properties.push(materialize(spec, &rule.conditions, &picked)?);
+			}
+		}
+	}
+
+	evaluation.permitted = match policy.section(SectionKind::Authorization) {
+		None => true,
+		Some(section) => {
+			let mut decision = None;
+			for rule in &section.rules {
+				let combinations = matching_claims(&rule.conditions, &incoming);
+				if combinations.is_empty() {
+					continue
+				}
+				match &rule.action {
+					Action::Permit => decision = Some(true),
+					Action::Deny => decision = Some(false),
+					Action::Add(spec) => {
+						let added = combinations
+							.iter()
+							.map(|picked| materialize(spec, &rule.conditions, picked))
+							.collect::<Result<Vec<_>, _>>()?;
+						incoming.extend(added);
+					},
+					action =>
+						return Err(format!(
+							"{}: {} is not valid in authorizationrules",
+							rule.position, action
+						)),
+				}
+				if decision.is_some() {
+					break
+				}
+			}
+			decision.unwrap_or(false)
+		},
+	};
+	if !evaluation.permitted {
+		return Ok(evaluation)
+	}
+
+	if let Some(section) = policy.section(SectionKind::Issuance) {
+		for rule in &section.rules {
+			let (spec, issue) = match &rule.action {
+				Action::Issue(spec) => (spec, true),
+				Action::Add(spec) => (spec, false),
+				action =>
+					return Err(format!(
+						"{}: {} is not valid in issuancerules",
+						rule.position, action
+					)),
+			};
+			let claims = matching_claims(&rule.conditions, &incoming)
+				.iter()
+				.map(|picked| materialize(spec, &rule.conditions, picked))
+				.collect::<Result<Vec<_>, _>>()?;
+			if issue {
+				evaluation.issued.extend(claims);
+			} else {
+				incoming.extend(claims);
+			}
+		}
+	}
+
+	Ok(evaluation)
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::{fs, path::Path};
+
+	use super::*;
+	use crate::{
+		enclaves::{
+			evidence,
+			inspect::ParsedEvidence,
+			model::{AttestationType, DataType, RuntimeData},
+			quote::{parse_open_enclave_report, SgxQuote},
+		},
+		policy::{claims::sgx_claims, parse},
+		utils::base64,
+	};
+	use serde_json::json;
+
+	const SGX_QUOTE: &str = include_str!("../../quotes/sgx_enclave_quote.txt");
+	const SGX_EHD: &str = include_str!("../../quotes/sgx_enclave_ehd.txt");
+	const OE_REPORT: &str = include_str!("../../quotes/open_enclave_quote.txt");
+
+	const POLICY: &str = r#"version=1.0;
+authorizationrules
+{
+	c:[type=="x-ms-sgx-is-debuggable", value==true] => deny();
+	c:[type=="x-ms-sgx-mrsigner", value=="feb995eb86c349ac98e5afbbb5732ca7376ec9979002702ea17ad476e0853a04"] => permit();
+};
+issuancerules
+{
+	c:[type=="x-ms-sgx-svn", value>=8000] => add(type="patched", value=true);
+	c:[type=="patched"] && s:[type=="x-ms-sgx-mrenclave"] => issue(type="enclave", value=s.value);
+	c:[type=="x-ms-sgx-ehd"] => issue(claim=c);
+	=> issue(type="policy", value="corpus");
+	=> issue(type="policy", value="regression");
+};
+"#;
+
+	fn sgx_quote_claims() -> Vec<Claim> {
+		let quote = SgxQuote::parse(&hex::decode(SGX_QUOTE.trim()).unwrap()).unwrap();
+		let ehd = base64(hex::decode(SGX_EHD.trim()).unwrap());
+		let runtime_data = RuntimeData::new(ehd, DataType::Binary);
+		sgx_claims(&quote, AttestationType::SgxEnclave, Some(&runtime_data)).unwrap()
+	}
+
+	#[test]
+	fn sgx_quote_is_permitted() {
+		let policy = parse(POLICY).unwrap();
+		let evaluation = evaluate(&policy, sgx_quote_claims()).unwrap();
+
+		assert!(evaluation.permitted);
+		assert_eq!(
+			Json::Object(evaluation.claims()),
+			json!({
+				"enclave": "d37d983a85d63fb49649610e2eba0930ecdbff6d113aca3ff3fc7261696c0134",
+				"x-ms-sgx-ehd": "AQIDBAUG",
+				"policy": ["corpus", "regression"],
+			})
+		);
+	}
+
+	#[test]
+	fn open_enclave_report_is_denied() {
+		let quote = parse_open_enclave_report(&hex::decode(OE_REPORT.trim()).unwrap()).unwrap();
+		let claims = sgx_claims(&quote, AttestationType::OpenEnclave, None).unwrap();
+
+		let evaluation = evaluate(&parse(POLICY).unwrap(), claims).unwrap();
+		assert!(!evaluation.permitted);
+		assert!(evaluation.issued.is_empty());
+	}
+
+	// Every `*quote*` file of `quotes/`, with the runtime data of its `*ehd*` sibling if any, so
+	// that corpus additions are evaluated without changes here.
+	#[test]
+	fn quote_corpus_is_evaluated() {
+		let policy = parse(POLICY).unwrap();
+		let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("quotes");
+		let mut evaluated = 0;
+		for entry in fs::read_dir(corpus).unwrap() {
+			let path = entry.unwrap().path();
+			let name = path.file_name().unwrap().to_string_lossy().to_string();
+			if !name.contains("quote") {
+				continue
+			}
+			let (tee, quote) = match ParsedEvidence::detect(&evidence::read(&path).unwrap().1) {
+				Ok(ParsedEvidence::Sgx(quote)) => (AttestationType::SgxEnclave, quote),
+				Ok(ParsedEvidence::OpenEnclave(quote)) => (AttestationType::OpenEnclave, quote),
+				Ok(other) => panic!("{}: no incoming claims for {} evidence", name, other.kind()),
+				Err(err) => panic!("{}: {}", name, err),
+			};
+			let ehd = path.with_file_name(name.replace("quote", "ehd"));
+			let runtime_data = ehd.exists().then(|| {
+				RuntimeData::new(base64(evidence::read(&ehd).unwrap().1), DataType::Binary)
+			});
+
+			let claims = sgx_claims(&quote, tee, runtime_data.as_ref())
+				.unwrap_or_else(|e| panic!("{}: {}", name, e));
+			let evaluation =
+				evaluate(&policy, claims).unwrap_or_else(|e| panic!("{}: {}", name, e));
+			if evaluation.permitted {
+				let issued_ehd = evaluation.claims().contains_key("x-ms-sgx-ehd");
+				assert_eq!(issued_ehd, runtime_data.is_some(), "{}", name);
+			} else {
+				assert!(evaluation.issued.is_empty(), "{}", name);
+			}
+			evaluated += 1;
+		}
+		assert!(evaluated >= 3, "only {} quotes in the corpus", evaluated);
+	}
+
+	#[test]
+	fn first_decision_wins() {
+		let policy = parse(
+			"version=1.0; authorizationrules { [type==\"$is-debuggable\", value==false] => deny(); \
+			 => permit(); };",
+		)
+		.unwrap();
+		assert!(!evaluate(&policy, sgx_quote_claims()).unwrap().permitted);
+		assert!(evaluate(&policy, vec![]).unwrap().permitted);
+	}
+}
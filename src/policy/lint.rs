@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use super::ast::{Action, ClaimSpec, Expr, Policy, Position, SectionKind};
+use crate::enclaves::model::AttestationType;
+
+/// Incoming claims MAA produces from SGX and OpenEnclave evidence.
+pub const SGX_CLAIMS: &[&str] = &[
+	"x-ms-attestation-type",
+	"x-ms-policy-hash",
+	"x-ms-sgx-is-debuggable",
+	"x-ms-sgx-product-id",
+	"x-ms-sgx-mrsigner",
+	"x-ms-sgx-mrenclave",
+	"x-ms-sgx-svn",
+	"x-ms-sgx-ehd",
+	"x-ms-sgx-collateral",
+	"x-ms-sgx-config-id",
+	"x-ms-sgx-config-svn",
+	"x-ms-sgx-isv-extended-product-id",
+	"x-ms-sgx-isv-family-id",
+	"x-ms-sgx-report-data",
+	"x-ms-runtime",
+	"x-ms-inittime",
+	"x-ms-ver",
+	"$is-debuggable",
+	"$sgx-mrsigner",
+	"$sgx-mrenclave",
+	"$product-id",
+	"$svn",
+	"$tee",
+];
+
+/// Incoming claims MAA produces from SEV-SNP evidence.
+pub const SEV_SNP_CLAIMS: &[&str] = &[
+	"x-ms-attestation-type",
+	"x-ms-compliance-status",
+	"x-ms-policy-hash",
+	"x-ms-sevsnpvm-authorkeydigest",
+	"x-ms-sevsnpvm-bootloader-svn",
+	"x-ms-sevsnpvm-familyId",
+	"x-ms-sevsnpvm-guestsvn",
+	"x-ms-sevsnpvm-hostdata",
+	"x-ms-sevsnpvm-idkeydigest",
+	"x-ms-sevsnpvm-imageId",
+	"x-ms-sevsnpvm-is-debuggable",
+	"x-ms-sevsnpvm-launchmeasurement",
+	"x-ms-sevsnpvm-microcode-svn",
+	"x-ms-sevsnpvm-migration-allowed",
+	"x-ms-sevsnpvm-reportdata",
+	"x-ms-sevsnpvm-reportid",
+	"x-ms-sevsnpvm-smt-allowed",
+	"x-ms-sevsnpvm-snpfw-svn",
+	"x-ms-sevsnpvm-tee-svn",
+	"x-ms-sevsnpvm-vmpl",
+	"x-ms-runtime",
+	"x-ms-inittime",
+	"x-ms-ver",
+];
+
+/// Policy language versions the service accepts.
+pub const VERSIONS: &[&str] = &["1.0", "1.1", "1.2"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+	pub message: String,
+	pub position: Position,
+}
+
+impl std::fmt::Display for Warning {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.position, self.message)
+	}
+}
+
+/// The incoming claim types MAA produces for `tee`, `None` if they are not known to this crate.
+pub fn known_claims(tee: AttestationType) -> Option<&'static [&'static str]> {
+	match tee {
+		AttestationType::SgxEnclave | AttestationType::OpenEnclave => Some(SGX_CLAIMS),
+		AttestationType::SevSnpVm => Some(SEV_SNP_CLAIMS),
+		AttestationType::Tpm => None,
+	}
+}
+
+/// Check a parsed policy for likely mistakes that the service would reject or silently ignore.
+pub fn lint(policy: &Policy, tee: AttestationType) -> Vec<Warning> {
+	let mut warnings = Vec::new();
+	let mut warn =
+		|message: String, position: Position| warnings.push(Warning { message, position });
+
+	if !VERSIONS.contains(&policy.version.as_str()) {
+		warn(format!("Unknown policy version {}", policy.version), Position { line: 1, column: 1 });
+	}
+
+	let mut seen = HashSet::new();
+	for section in &policy.sections {
+		if !seen.insert(section.kind) {
+			warn(format!("Duplicate {} section", section.kind.keyword()), section.position);
+		}
+	}
+
+	// Claims added by earlier rules are incoming claims for later ones.
+	let mut added: HashSet<&str> = HashSet::new();
+	for section in &policy.sections {
+		for rule in &section.rules {
+			if section.kind != SectionKind::Configuration {
+				for condition in &rule.conditions {
+					let Some(claim_type) = condition.claim_type() else { continue };
+					let known =
+						known_claims(tee).map_or(true, |claims| claims.contains(&claim_type));
+					if !known && !added.contains(claim_type) {
+						warn(
+							format!("Unknown claim type \"{}\" for {}", claim_type, tee),
+							condition.position,
+						);
+					}
+				}
+			}
+
+			let bindings: Vec<&str> =
+				rule.conditions.iter().filter_map(|c| c.binding.as_deref()).collect();
+			let spec = match &rule.action {
+				Action::Issue(spec) | Action::Add(spec) | Action::IssueProperty(spec) => spec,
+				Action::Permit | Action::Deny => continue,
+			};
+			let referenced = match spec {
+				ClaimSpec::Claim(binding) => Some(binding.as_str()),
+				ClaimSpec::TypeValue { value: Expr::Reference { binding, .. }, .. } =>
+					Some(binding.as_str()),
+				ClaimSpec::TypeValue { .. } => None,
+			};
+			if let Some(binding) = referenced.filter(|binding| !bindings.contains(binding)) {
+				warn(format!("Reference to unbound claim `{}`", binding), rule.position);
+			}
+
+			match (&rule.action, spec) {
+				(Action::IssueProperty(_), _) if section.kind != SectionKind::Configuration =>
+					warn(
+						"issueproperty() is only valid in configurationrules".into(),
+						rule.position,
+					),
+				(Action::Add(_), ClaimSpec::TypeValue { claim_type, .. }) => {
+					added.insert(claim_type.as_str());
+				},
+				_ => {},
+			}
+		}
+	}
+
+	warnings
+}
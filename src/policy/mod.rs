@@ -0,0 +1,13 @@
+//! The MAA attestation policy language: parsing, linting, canonical printing and local evaluation
+//! of policy text, and the signed documents policies are set with.
+
+pub mod ast;
+pub mod claims;
+pub mod document;
+pub mod evaluator;
+pub mod lint;
+pub mod parser;
+
+pub use evaluator::evaluate;
+pub use lint::lint;
+pub use parser::parse;
@@ -0,0 +1,468 @@
+use std::fmt;
+
+use super::ast::{
+	Action, ClaimSpec, Condition, Expr, Matcher, Operator, Policy, Position, Property, Rule,
+	Section, SectionKind, Value,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+	pub message: String,
+	pub position: Position,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.position, self.message)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	Ident(String),
+	Str(String),
+	Number(String),
+	Symbol(&'static str),
+	End,
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Token::Ident(ident) => write!(f, "`{}`", ident),
+			Token::Str(s) => write!(f, "\"{}\"", s),
+			Token::Number(n) => write!(f, "`{}`", n),
+			Token::Symbol(symbol) => write!(f, "`{}`", symbol),
+			Token::End => write!(f, "end of policy"),
+		}
+	}
+}
+
+// Longest symbols first, so that `==` is not lexed as two `=`.
+const SYMBOLS: [&str; 19] = [
+	"=>", "==", "!=", "<=", ">=", "&&", "=", "<", ">", "!", "[", "]", "{", "}", "(", ")", ",", ";",
+	":",
+];
+
+fn tokenize(text: &str) -> Result<Vec<(Token, Position)>, ParseError> {
+	let chars: Vec<char> = text.chars().collect();
+	let mut tokens = Vec::new();
+	let (mut i, mut line, mut column) = (0, 1, 1);
+
+	while i < chars.len() {
+		let position = Position { line, column };
+		let c = chars[i];
+		let start = i;
+
+		if c == '\n' {
+			i += 1;
+			line += 1;
+			column = 1;
+			continue
+		}
+		if c.is_whitespace() {
+			i += 1;
+			column += 1;
+			continue
+		}
+		if c == '/' && chars.get(i + 1) == Some(&'/') {
+			while i < chars.len() && chars[i] != '\n' {
+				i += 1;
+			}
+			continue
+		}
+
+		if c == '"' {
+			let mut s = String::new();
+			i += 1;
+			loop {
+				match chars.get(i) {
+					None | Some('\n') =>
+						return Err(ParseError { message: "Unterminated string".into(), position }),
+					Some('"') => break,
+					Some('\\') if i + 1 < chars.len() => {
+						s.push(chars[i + 1]);
+						i += 2;
+					},
+					Some(c) => {
+						s.push(*c);
+						i += 1;
+					},
+				}
+			}
+			i += 1;
+			tokens.push((Token::Str(s), position));
+		} else if c.is_ascii_digit() ||
+			(c == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+		{
+			i += 1;
+			while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+				i += 1;
+			}
+			tokens.push((Token::Number(chars[start..i].iter().collect()), position));
+		} else if c.is_ascii_alphabetic() || c == '_' {
+			while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+				i += 1;
+			}
+			tokens.push((Token::Ident(chars[start..i].iter().collect()), position));
+		} else if c == '.' {
+			i += 1;
+			tokens.push((Token::Symbol("."), position));
+		} else {
+			let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+			let symbol = SYMBOLS
+				.iter()
+				.find(|symbol| rest.starts_with(**symbol))
+				.ok_or(ParseError { message: format!("Unexpected character `{}`", c), position })?;
+			i += symbol.len();
+			tokens.push((Token::Symbol(symbol), position));
+		}
+		column += i - start;
+	}
+
+	tokens.push((Token::End, Position { line, column }));
+	Ok(tokens)
+}
+
+struct Parser {
+	tokens: Vec<(Token, Position)>,
+	next: usize,
+}
+
+impl Parser {
+	fn peek(&self) -> &Token {
+		&self.tokens[self.next].0
+	}
+
+	fn position(&self) -> Position {
+		self.tokens[self.next].1
+	}
+
+	fn advance(&mut self) -> Token {
+		let token = self.tokens[self.next].0.clone();
+		if token != Token::End {
+			self.next += 1;
+		}
+		token
+	}
+
+	fn error<T>(&self, expected: &str) -> Result<T, ParseError> {
+		Err(ParseError {
+			message: format!("Expected {}, found {}", expected, self.peek()),
+			position: self.position(),
+		})
+	}
+
+	fn is_symbol(&self, symbol: &str) -> bool {
+		matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+	}
+
+	fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+		if !self.is_symbol(symbol) {
+			return self.error(&format!("`{}`", symbol))
+		}
+		self.advance();
+		Ok(())
+	}
+
+	fn expect_ident(&mut self, what: &str) -> Result<String, ParseError> {
+		match self.peek() {
+			Token::Ident(_) => match self.advance() {
+				Token::Ident(ident) => Ok(ident),
+				_ => unreachable!(),
+			},
+			_ => self.error(what),
+		}
+	}
+
+	fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+		match self.peek() {
+			Token::Ident(ident) if ident.eq_ignore_ascii_case(keyword) => {
+				self.advance();
+				Ok(())
+			},
+			_ => self.error(&format!("`{}`", keyword)),
+		}
+	}
+
+	fn policy(&mut self) -> Result<Policy, ParseError> {
+		self.expect_keyword("version")?;
+		self.expect_symbol("=")?;
+		let version = match self.peek() {
+			Token::Number(version) => version.clone(),
+			_ => return self.error("a version number"),
+		};
+		self.advance();
+		self.expect_symbol(";")?;
+
+		let mut sections = Vec::new();
+		while *self.peek() != Token::End {
+			sections.push(self.section()?);
+		}
+		Ok(Policy { version, sections })
+	}
+
+	fn section(&mut self) -> Result<Section, ParseError> {
+		let position = self.position();
+		let kind = match self.peek() {
+			Token::Ident(ident) => match ident.to_ascii_lowercase().as_str() {
+				"configurationrules" => SectionKind::Configuration,
+				"authorizationrules" => SectionKind::Authorization,
+				"issuancerules" => SectionKind::Issuance,
+				_ => return self.error("a rule section"),
+			},
+			_ => return self.error("a rule section"),
+		};
+		self.advance();
+
+		self.expect_symbol("{")?;
+		let mut rules = Vec::new();
+		while !self.is_symbol("}") {
+			rules.push(self.rule()?);
+		}
+		self.expect_symbol("}")?;
+		self.expect_symbol(";")?;
+
+		Ok(Section { kind, rules, position })
+	}
+
+	fn rule(&mut self) -> Result<Rule, ParseError> {
+		let position = self.position();
+		let mut conditions = Vec::new();
+		if !self.is_symbol("=>") {
+			conditions.push(self.condition()?);
+			while self.is_symbol("&&") {
+				self.advance();
+				conditions.push(self.condition()?);
+			}
+		}
+		self.expect_symbol("=>")?;
+		let action = self.action()?;
+		self.expect_symbol(";")?;
+
+		Ok(Rule { conditions, action, position })
+	}
+
+	fn condition(&mut self) -> Result<Condition, ParseError> {
+		let position = self.position();
+		let binding = match self.peek() {
+			Token::Ident(_) => {
+				let binding = self.expect_ident("a claim binding")?;
+				self.expect_symbol(":")?;
+				Some(binding)
+			},
+			_ => None,
+		};
+
+		self.expect_symbol("[")?;
+		let mut matchers = vec![self.matcher()?];
+		while self.is_symbol(",") {
+			self.advance();
+			matchers.push(self.matcher()?);
+		}
+		self.expect_symbol("]")?;
+
+		Ok(Condition { binding, matchers, position })
+	}
+
+	fn property(&mut self) -> Result<Property, ParseError> {
+		match self.peek() {
+			Token::Ident(ident) => {
+				let property = match ident.to_ascii_lowercase().as_str() {
+					"type" => Property::Type,
+					"value" => Property::Value,
+					"issuer" => Property::Issuer,
+					_ => return self.error("`type`, `value` or `issuer`"),
+				};
+				self.advance();
+				Ok(property)
+			},
+			_ => self.error("`type`, `value` or `issuer`"),
+		}
+	}
+
+	fn matcher(&mut self) -> Result<Matcher, ParseError> {
+		let position = self.position();
+		let property = self.property()?;
+		let operator = match self.peek() {
+			Token::Symbol("==") => Operator::Equal,
+			Token::Symbol("!=") => Operator::NotEqual,
+			Token::Symbol("<") => Operator::Less,
+			Token::Symbol("<=") => Operator::LessOrEqual,
+			Token::Symbol(">") => Operator::Greater,
+			Token::Symbol(">=") => Operator::GreaterOrEqual,
+			_ => return self.error("a comparison operator"),
+		};
+		self.advance();
+		let value = self.value()?;
+
+		if property != Property::Value && !matches!(value, Value::String(_)) {
+			return Err(ParseError {
+				message: format!("Claim {} must be compared to a string", property),
+				position,
+			})
+		}
+		Ok(Matcher { property, operator, value, position })
+	}
+
+	fn value(&mut self) -> Result<Value, ParseError> {
+		let position = self.position();
+		let value = match self.peek() {
+			Token::Str(s) => Value::String(s.clone()),
+			Token::Number(n) => Value::Integer(n.parse().map_err(|_| ParseError {
+				message: format!("Invalid integer `{}`", n),
+				position,
+			})?),
+			Token::Ident(ident) if ident.eq_ignore_ascii_case("true") => Value::Boolean(true),
+			Token::Ident(ident) if ident.eq_ignore_ascii_case("false") => Value::Boolean(false),
+			_ => return self.error("a string, integer or boolean"),
+		};
+		self.advance();
+		Ok(value)
+	}
+
+	fn expr(&mut self) -> Result<Expr, ParseError> {
+		match self.peek() {
+			Token::Ident(ident)
+				if !ident.eq_ignore_ascii_case("true") && !ident.eq_ignore_ascii_case("false") =>
+			{
+				let binding = self.expect_ident("a claim binding")?;
+				self.expect_symbol(".")?;
+				let property = self.property()?;
+				Ok(Expr::Reference { binding, property })
+			},
+			_ => self.value().map(Expr::Literal),
+		}
+	}
+
+	fn action(&mut self) -> Result<Action, ParseError> {
+		let position = self.position();
+		let name = self.expect_ident("an action")?.to_ascii_lowercase();
+		self.expect_symbol("(")?;
+		let action = match name.as_str() {
+			"permit" => Action::Permit,
+			"deny" => Action::Deny,
+			"issue" => Action::Issue(self.claim_spec()?),
+			"add" => Action::Add(self.claim_spec()?),
+			"issueproperty" => Action::IssueProperty(self.claim_spec()?),
+			_ => return Err(ParseError { message: format!("Unknown action `{}`", name), position }),
+		};
+		self.expect_symbol(")")?;
+		Ok(action)
+	}
+
+	fn claim_spec(&mut self) -> Result<ClaimSpec, ParseError> {
+		let position = self.position();
+		let mut claim = None;
+		let mut claim_type = None;
+		let mut value = None;
+
+		loop {
+			let argument = self.expect_ident("`claim`, `type` or `value`")?.to_ascii_lowercase();
+			self.expect_symbol("=")?;
+			match argument.as_str() {
+				"claim" => claim = Some(self.expect_ident("a claim binding")?),
+				"type" => match self.value()? {
+					Value::String(s) => claim_type = Some(s),
+					_ => return self.error("a claim type string"),
+				},
+				"value" => value = Some(self.expr()?),
+				_ =>
+					return Err(ParseError {
+						message: format!("Unknown argument `{}`", argument),
+						position,
+					}),
+			}
+			if !self.is_symbol(",") {
+				break
+			}
+			self.advance();
+		}
+
+		match (claim, claim_type, value) {
+			(Some(binding), None, None) => Ok(ClaimSpec::Claim(binding)),
+			(None, Some(claim_type), Some(value)) => Ok(ClaimSpec::TypeValue { claim_type, value }),
+			_ => Err(ParseError {
+				message: "Expected either `claim=` or both `type=` and `value=`".into(),
+				position,
+			}),
+		}
+	}
+}
+
+/// Parse MAA policy text.
+pub fn parse(text: &str) -> Result<Policy, ParseError> {
+	let tokens = tokenize(text)?;
+	Parser { tokens, next: 0 }.policy()
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::{enclaves::model::AttestationType, policy::lint};
+
+	const POLICY: &str = r#"version= 1.0;
+authorizationrules
+{
+	c:[type=="$is-debuggable"] => deny();
+	[type=="x-ms-sgx-mrsigner", value=="4aea5f9a0ed04b11f889aadfe6a1d376213a29a95a85ce7337ff6e2d1ad2ba2a"] => permit();
+};
+issuancerules
+{
+	c:[type=="x-ms-sgx-mrsigner"] => issue(type="signer", value=c.value);
+	c:[type=="x-ms-sgx-svn", value>=2] => add(type="patched", value=true);
+	c1:[type=="patched"] && c2:[type=="x-ms-sgx-product-id"] => issue(claim=c2);
+};
+"#;
+
+	#[test]
+	fn parse_works() {
+		let policy = parse(POLICY).unwrap();
+		assert_eq!(policy.version, "1.0");
+		assert_eq!(policy.sections.len(), 2);
+
+		let issuance = policy.section(SectionKind::Issuance).unwrap();
+		assert_eq!(issuance.rules[1].conditions[0].matchers[1].value, Value::Integer(2));
+		assert_eq!(issuance.rules[2].action, Action::Issue(ClaimSpec::Claim("c2".into())));
+		assert_eq!(issuance.rules[2].position, Position { line: 11, column: 2 });
+
+		assert!(lint(&policy, AttestationType::SgxEnclave).is_empty());
+	}
+
+	#[test]
+	fn canonical_form_round_trips() {
+		let canonical = parse(POLICY).unwrap().to_string();
+		assert!(canonical.starts_with(
+			"version=1.0;\nauthorizationrules\n{\n    c:[type==\"$is-debuggable\"] => deny();\n"
+		));
+		assert_eq!(parse(&canonical).unwrap().to_string(), canonical);
+	}
+
+	#[test]
+	fn syntax_errors_have_positions() {
+		let err = parse("version=1.0;\nauthorizationrules\n{\n  => permit()\n};").unwrap_err();
+		assert_eq!(err.position, Position { line: 5, column: 1 });
+		assert_eq!(err.message, "Expected `;`, found `}`");
+
+		let err = parse("version=1.0;\nissuancerules { [type=\"x\"] => permit(); };").unwrap_err();
+		assert_eq!(err.position, Position { line: 2, column: 22 });
+	}
+
+	#[test]
+	fn lint_warns_on_unknown_claims() {
+		let policy = parse(
+			"version=1.0; authorizationrules { [type==\"x-ms-sgx-mrenclav\"] => permit(); \
+			 => issue(type=\"x\", value=c.value); };",
+		)
+		.unwrap();
+		let warnings = lint(&policy, AttestationType::SgxEnclave);
+		assert_eq!(warnings.len(), 2);
+		assert_eq!(warnings[0].message, "Unknown claim type \"x-ms-sgx-mrenclav\" for SgxEnclave");
+		assert_eq!(warnings[1].message, "Reference to unbound claim `c`");
+
+		assert_eq!(lint(&policy, AttestationType::SevSnpVm).len(), 2);
+		assert_eq!(lint(&policy, AttestationType::Tpm).len(), 1);
+	}
+}
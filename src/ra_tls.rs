@@ -0,0 +1,318 @@
+//! RA-TLS: TLS certificates that carry the MAA token of the enclave holding their key, so that
+//! peers can tell from the TLS handshake alone which enclave they talk to.
+//!
+//! The enclave generates a key pair and attests itself with SHA-256 of the public key as runtime
+//! data, so that the issued token carries the key hash in its enclave held data claim. It then
+//! issues itself a certificate for the key with the token in an extension. Peers verify the token,
+//! that it names the certificate key, and what it says about the enclave; the TLS handshake then
+//! proves the enclave holds the key.
+
+use ring::{
+	digest::{digest, SHA256},
+	rand::SystemRandom,
+	signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+};
+
+use crate::{
+	enclaves::{binding, enclave_info::EnclaveInfo},
+	service::client::Client,
+	utils::AttestationResult,
+	x509,
+};
+
+/// Object identifier of the certificate extension carrying the token, whose value is the token
+/// text. A private identifier: enclaves and their peers only need to agree on it.
+pub const TOKEN_EXTENSION_OID: &[u64] = &[2, 25, 15_848_921_583_104_812_561];
+
+/// A P-256 key pair of an enclave, to attest and to certify.
+pub struct RaTlsIdentity {
+	pkcs8: Vec<u8>,
+}
+
+impl RaTlsIdentity {
+	pub fn generate() -> Result<Self, String> {
+		let pkcs8 =
+			EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+				.map_err(|e| e.to_string())?;
+		Ok(Self { pkcs8: pkcs8.as_ref().to_vec() })
+	}
+
+	/// The identity of a P-256 key kept by the enclave, e.g. a sealed one.
+	pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, String> {
+		EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8)
+			.map_err(|e| e.to_string())?;
+		Ok(Self { pkcs8: pkcs8.to_vec() })
+	}
+
+	fn key_pair(&self) -> Result<rcgen::KeyPair, String> {
+		rcgen::KeyPair::from_der(&self.pkcs8).map_err(|e| e.to_string())
+	}
+
+	/// The runtime data to attest the enclave with: SHA-256 of the DER encoded
+	/// SubjectPublicKeyInfo of the key.
+	pub fn runtime_data(&self) -> Result<[u8; 32], String> {
+		let mut hash = [0u8; 32];
+		hash.copy_from_slice(digest(&SHA256, &self.key_pair()?.public_key_der()).as_ref());
+		Ok(hash)
+	}
+
+	/// A self-signed certificate for the key and the DNS name `name`, carrying `token`, the MAA
+	/// token issued for `runtime_data`.
+	pub fn certificate(&self, token: &str, name: &str) -> Result<RaTlsCertificate, String> {
+		let mut params = rcgen::CertificateParams::new(vec![name.to_string()]);
+		params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+		params.key_pair = Some(self.key_pair()?);
+		params.custom_extensions.push(rcgen::CustomExtension::from_oid_content(
+			TOKEN_EXTENSION_OID,
+			token.trim().as_bytes().to_vec(),
+		));
+		let certificate = rcgen::Certificate::from_params(params).map_err(|e| e.to_string())?;
+
+		Ok(RaTlsCertificate {
+			certificate: certificate.serialize_der().map_err(|e| e.to_string())?,
+			private_key: self.pkcs8.clone(),
+		})
+	}
+}
+
+/// A certificate carrying an MAA token, and its PKCS #8 private key, both DER encoded.
+#[derive(Clone, Debug)]
+pub struct RaTlsCertificate {
+	pub certificate: Vec<u8>,
+	pub private_key: Vec<u8>,
+}
+
+impl RaTlsCertificate {
+	/// The certificate chain to hand `rustls`, e.g. to `ServerConfig::set_single_cert`.
+	pub fn chain(&self) -> Vec<rustls::Certificate> {
+		vec![rustls::Certificate(self.certificate.clone())]
+	}
+
+	pub fn key(&self) -> rustls::PrivateKey {
+		rustls::PrivateKey(self.private_key.clone())
+	}
+}
+
+/// The MAA token of an RA-TLS certificate, unverified.
+pub fn embedded_token(certificate: &[u8]) -> Result<String, String> {
+	let token = x509::extension(certificate, TOKEN_EXTENSION_OID)?
+		.ok_or("Certificate carries no MAA token")?;
+	String::from_utf8(token.to_vec()).map_err(|_| "Invalid MAA token extension".to_string())
+}
+
+/// Verifies RA-TLS certificates of peers, as server or as client certificates. Certificate chains
+/// and names are not checked: the token is what vouches for the key.
+pub struct RaTlsVerifier {
+	client: Client,
+	expected: Option<EnclaveInfo>,
+}
+
+impl RaTlsVerifier {
+	/// A verifier checking tokens with the signing keys of the providers of `client`.
+	pub fn new(client: Client) -> Self {
+		Self { client, expected: None }
+	}
+
+	/// Require the enclave to match `expected`. Its enclave held data is not compared: in RA-TLS
+	/// that is the certificate key hash.
+	#[must_use]
+	pub fn expect(mut self, expected: EnclaveInfo) -> Self {
+		self.expected = Some(expected);
+		self
+	}
+
+	/// Verify the token of the DER encoded `certificate`, its binding to the certificate key and
+	/// the expectations, returning the claims of the token.
+	pub fn verify_certificate(&self, certificate: &[u8]) -> Result<AttestationResult, String> {
+		let token = embedded_token(certificate)?;
+		let result = self.client.verify_token(&token)?;
+
+		let mut key_hash = [0u8; 32];
+		key_hash.copy_from_slice(digest(&SHA256, x509::spki(certificate)?).as_ref());
+		binding::verify_claim(&key_hash, &result)
+			.map_err(|_| "MAA token is not bound to the certificate key".to_string())?;
+
+		if let Some(expected) = &self.expected {
+			let mismatches: Vec<String> = expected
+				.checks(&result)
+				.into_iter()
+				.filter(|check| check.name != "enclave-held-data" && !check.passed)
+				.map(|check| {
+					format!(
+						"{} expected {}, MAA attested {}",
+						check.name, check.expected, check.actual
+					)
+				})
+				.collect();
+			if !mismatches.is_empty() {
+				return Err(format!("Enclave does not match: {}", mismatches.join(", ")))
+			}
+		}
+		Ok(result)
+	}
+
+	fn verify_chain(
+		&self,
+		presented_certs: &[rustls::Certificate],
+	) -> Result<(), rustls::TLSError> {
+		let certificate =
+			presented_certs.first().ok_or(rustls::TLSError::NoCertificatesPresented)?;
+		self.verify_certificate(&certificate.0).map_err(rustls::TLSError::General)?;
+		Ok(())
+	}
+}
+
+impl rustls::ServerCertVerifier for RaTlsVerifier {
+	fn verify_server_cert(
+		&self,
+		_roots: &rustls::RootCertStore,
+		presented_certs: &[rustls::Certificate],
+		_dns_name: webpki::DNSNameRef,
+		_ocsp_response: &[u8],
+	) -> Result<rustls::ServerCertVerified, rustls::TLSError> {
+		self.verify_chain(presented_certs)?;
+		Ok(rustls::ServerCertVerified::assertion())
+	}
+}
+
+impl rustls::ClientCertVerifier for RaTlsVerifier {
+	fn client_auth_root_subjects(
+		&self,
+		_sni: Option<&webpki::DNSName>,
+	) -> Option<rustls::DistinguishedNames> {
+		Some(rustls::DistinguishedNames::new())
+	}
+
+	fn verify_client_cert(
+		&self,
+		presented_certs: &[rustls::Certificate],
+		_sni: Option<&webpki::DNSName>,
+	) -> Result<rustls::ClientCertVerified, rustls::TLSError> {
+		self.verify_chain(presented_certs)?;
+		Ok(rustls::ClientCertVerified::assertion())
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::{
+		net::{TcpListener, TcpStream},
+		sync::Arc,
+		thread,
+	};
+
+	use rustls::Session;
+
+	use super::*;
+	use crate::{
+		service::{client::ClientBuilder, stand_in::SigningStandIn},
+		utils::base64url,
+	};
+
+	const ENCLAVE_INFO: &str = include_str!("../quotes/enclave.info.securityversion.json");
+
+	struct Provider(SigningStandIn);
+
+	impl Provider {
+		fn new() -> Self {
+			Self(SigningStandIn::serve())
+		}
+
+		fn token(&self, identity: &RaTlsIdentity, svn: u32) -> String {
+			self.0.token(serde_json::json!({
+				"x-ms-sgx-ehd": base64url(&identity.runtime_data().unwrap()),
+				"sgx-mrenclave": "d37d983a85d63fb49649610e2eba0930ecdbff6d113aca3ff3fc7261696c0134",
+				"sgx-mrsigner": "feb995eb86c349ac98e5afbbb5732ca7376ec9979002702ea17ad476e0853a04",
+				"svn": svn,
+				"is-debuggable": false,
+			}))
+		}
+
+		fn verifier(&self) -> RaTlsVerifier {
+			let client = ClientBuilder::new("token".into(), self.0.url()).build().unwrap();
+			RaTlsVerifier::new(client).expect(serde_json::from_str(ENCLAVE_INFO).unwrap())
+		}
+	}
+
+	#[test]
+	fn certificate_token_and_binding_are_verified() {
+		let provider = Provider::new();
+		let identity = RaTlsIdentity::generate().unwrap();
+		let token = provider.token(&identity, 8888);
+		let certificate = identity.certificate(&token, "enclave").unwrap();
+		assert_eq!(embedded_token(&certificate.certificate).unwrap(), token);
+
+		let result = provider.verifier().verify_certificate(&certificate.certificate).unwrap();
+		assert_eq!(result.x_ms_policy.svn, Some(8888));
+
+		// The token of another key.
+		let other = RaTlsIdentity::generate().unwrap().certificate(&token, "enclave").unwrap();
+		let err = provider.verifier().verify_certificate(&other.certificate).unwrap_err();
+		assert!(err.contains("not bound to the certificate key"), "{}", err);
+
+		let outdated = identity.certificate(&provider.token(&identity, 1), "enclave").unwrap();
+		let err = provider.verifier().verify_certificate(&outdated.certificate).unwrap_err();
+		assert_eq!(err, "Enclave does not match: svn expected 8888, MAA attested 1");
+
+		let tampered = identity.certificate(&token.replace(".ey", ".eX"), "enclave").unwrap();
+		assert!(provider.verifier().verify_certificate(&tampered.certificate).is_err());
+		assert!(embedded_token(provider.0.url().as_str().as_bytes()).is_err());
+	}
+
+	#[test]
+	fn mutual_ra_tls_handshake_works() {
+		let provider = Provider::new();
+		let server_identity = RaTlsIdentity::generate().unwrap();
+		let server_certificate = server_identity
+			.certificate(&provider.token(&server_identity, 8888), "enclave")
+			.unwrap();
+		let client_identity = RaTlsIdentity::generate().unwrap();
+		let client_certificate = client_identity
+			.certificate(&provider.token(&client_identity, 1), "client")
+			.unwrap();
+
+		let handshake = |client_verifier: RaTlsVerifier, server_verifier: RaTlsVerifier| {
+			let mut server_config = rustls::ServerConfig::new(Arc::new(server_verifier));
+			server_config
+				.set_single_cert(server_certificate.chain(), server_certificate.key())
+				.unwrap();
+			let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+			let port = listener.local_addr().unwrap().port();
+			let server = thread::spawn(move || {
+				let (mut stream, _) = listener.accept().unwrap();
+				let mut session = rustls::ServerSession::new(&Arc::new(server_config));
+				while session.is_handshaking() {
+					session.complete_io(&mut stream).map_err(|e| e.to_string())?;
+				}
+				Ok::<_, String>(session.get_peer_certificates().unwrap_or_default().len())
+			});
+
+			let mut client_config = rustls::ClientConfig::new();
+			client_config.dangerous().set_certificate_verifier(Arc::new(client_verifier));
+			client_config
+				.set_single_client_cert(client_certificate.chain(), client_certificate.key())
+				.unwrap();
+			let name = webpki::DNSNameRef::try_from_ascii_str("enclave").unwrap();
+			let mut session = rustls::ClientSession::new(&Arc::new(client_config), name);
+			let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+			let mut client = Ok(());
+			while session.is_handshaking() && client.is_ok() {
+				client = session.complete_io(&mut stream).map(|_| ()).map_err(|e| e.to_string());
+			}
+			// Send the final flight, with the client certificate.
+			while session.wants_write() && client.is_ok() {
+				client = session.write_tls(&mut stream).map(|_| ()).map_err(|e| e.to_string());
+			}
+			drop(stream);
+			(client, server.join().unwrap())
+		};
+
+		let lenient = RaTlsVerifier::new(provider.verifier().client.clone());
+		let (client, server) = handshake(provider.verifier(), lenient);
+		assert_eq!((client, server), (Ok(()), Ok(1)));
+
+		// The client enclave does not have the expected security version.
+		let (_, server) = handshake(provider.verifier(), provider.verifier());
+		assert!(server.unwrap_err().contains("svn expected 8888"));
+	}
+}
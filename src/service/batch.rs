@@ -0,0 +1,220 @@
+//! Attestation of many pieces of evidence against one provider, with bounded concurrency.
+
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+use super::{client::attestation, error::AttestError};
+use crate::{
+	enclaves::{
+		enclave_info::{Check, EnclaveInfo},
+		evidence::Evidence,
+		model::AttestationType,
+	},
+	utils::AttestationResult,
+};
+
+/// Number of attestations in flight at once, unless configured.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+#[derive(Debug)]
+pub struct BatchItem {
+	pub name: String,
+	pub tee: AttestationType,
+	pub evidence: Evidence,
+	/// What the attested enclave must match, SGX and OpenEnclave only.
+	pub expected: Option<EnclaveInfo>,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+	Passed(AttestationResult),
+	/// Attested, but not matching the expectations.
+	Mismatch(AttestationResult),
+	/// Rejected by the provider, or not attested at all.
+	Error(String),
+}
+
+impl Outcome {
+	/// The outcome of a verified attestation, or of its failure, against `expected`, with the
+	/// checks made.
+	pub fn of(
+		attested: Result<AttestationResult, String>,
+		expected: Option<&EnclaveInfo>,
+	) -> (Outcome, Vec<Check>) {
+		match attested {
+			Ok(result) => {
+				let checks = expected.map(|info| info.checks(&result)).unwrap_or_default();
+				if checks.iter().all(|check| check.passed) {
+					(Outcome::Passed(result), checks)
+				} else {
+					(Outcome::Mismatch(result), checks)
+				}
+			},
+			Err(err) => (Outcome::Error(err), vec![]),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct ItemResult {
+	pub name: String,
+	pub outcome: Outcome,
+	pub checks: Vec<Check>,
+	pub duration: Duration,
+}
+
+impl ItemResult {
+	pub fn passed(&self) -> bool {
+		matches!(self.outcome, Outcome::Passed(_))
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+	pub total: usize,
+	pub passed: usize,
+	pub mismatched: usize,
+	pub errors: usize,
+	pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+pub struct BatchReport {
+	/// One result per item, in the order of the items.
+	pub results: Vec<ItemResult>,
+	pub summary: Summary,
+}
+
+pub struct BatchAttestor {
+	client: attestation::Client,
+	concurrency: usize,
+	min_interval: Duration,
+}
+
+impl BatchAttestor {
+	pub fn new(client: attestation::Client) -> Self {
+		Self { client, concurrency: DEFAULT_CONCURRENCY, min_interval: Duration::ZERO }
+	}
+
+	#[doc = "Run at most `concurrency` attestations at once, at least one."]
+	#[must_use]
+	pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+		self.concurrency = concurrency.max(1);
+		self
+	}
+
+	#[doc = "Start at most `per_second` attestations per second, whatever the concurrency."]
+	#[must_use]
+	pub fn with_rate_limit(mut self, per_second: u32) -> Self {
+		self.min_interval = Duration::from_secs(1) / per_second.max(1);
+		self
+	}
+
+	/// Attest every item and check it against its expectations.
+	pub fn run(&self, items: &[BatchItem]) -> BatchReport {
+		let started = Instant::now();
+		let next_item = AtomicUsize::new(0);
+		let next_start = Mutex::new(started);
+		let results: Mutex<Vec<Option<ItemResult>>> =
+			Mutex::new(items.iter().map(|_| None).collect());
+
+		thread::scope(|scope| {
+			for _ in 0..self.concurrency.min(items.len()) {
+				scope.spawn(|| loop {
+					let index = next_item.fetch_add(1, Ordering::SeqCst);
+					let Some(item) = items.get(index) else { break };
+
+					// Reserve the next start slot, then wait for it outside the lock.
+					let start = {
+						let mut next_start = next_start.lock().unwrap();
+						let start = (*next_start).max(Instant::now());
+						*next_start = start + self.min_interval;
+						start
+					};
+					thread::sleep(start.saturating_duration_since(Instant::now()));
+
+					let result = self.attest(item);
+					results.lock().unwrap()[index] = Some(result);
+				});
+			}
+		});
+
+		let results: Vec<ItemResult> =
+			results.into_inner().unwrap().into_iter().flatten().collect();
+		let mut summary = Summary { total: results.len(), ..Default::default() };
+		for result in &results {
+			match result.outcome {
+				Outcome::Passed(_) => summary.passed += 1,
+				Outcome::Mismatch(_) => summary.mismatched += 1,
+				Outcome::Error(_) => summary.errors += 1,
+			}
+		}
+		summary.elapsed = started.elapsed();
+
+		BatchReport { results, summary }
+	}
+
+	/// Attest one item and check it against its expectations.
+	pub fn attest(&self, item: &BatchItem) -> ItemResult {
+		let started = Instant::now();
+		let attested = match item.tee {
+			AttestationType::SgxEnclave =>
+				self.client.attest_sgx_enclave(item.evidence.sgx_request()).into_result(),
+			AttestationType::OpenEnclave => self
+				.client
+				.attest_open_enclave(item.evidence.open_enclave_request())
+				.into_result(),
+			AttestationType::SevSnpVm =>
+				self.client.attest_sev_snp_vm(item.evidence.sev_snp_request()).into_result(),
+			tee =>
+				Err(AttestError::Request(format!("Batch attestation of {} is not supported", tee))),
+		};
+
+		let (outcome, checks) = Outcome::of(attested.map_err(String::from), item.expected.as_ref());
+		ItemResult { name: item.name.clone(), outcome, checks, duration: started.elapsed() }
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::service::{client::ClientBuilder, stand_in::StandIn};
+
+	const SGX_QUOTE: &[u8] = include_bytes!("../../quotes/sgx_enclave_quote.txt");
+
+	#[test]
+	fn batch_is_rate_limited_and_ordered() {
+		let server = StandIn::serve(vec![("POST /attest/SgxEnclave", 400, "{}".into())]);
+		let client = ClientBuilder::new("token".into(), server.url()).build().unwrap();
+
+		let evidence = Evidence::from_bytes(SGX_QUOTE).unwrap();
+		let mut items: Vec<BatchItem> = (0..4)
+			.map(|i| BatchItem {
+				name: format!("enclave-{}", i),
+				tee: AttestationType::SgxEnclave,
+				evidence: evidence.clone(),
+				expected: None,
+			})
+			.collect();
+		items[3].tee = AttestationType::Tpm;
+
+		let report = BatchAttestor::new(client.attestation_client())
+			.with_concurrency(3)
+			.with_rate_limit(20)
+			.run(&items);
+
+		let names: Vec<&str> = report.results.iter().map(|r| r.name.as_str()).collect();
+		assert_eq!(names, ["enclave-0", "enclave-1", "enclave-2", "enclave-3"]);
+		assert!(matches!(&report.results[0].outcome, Outcome::Error(err) if err.contains("400")));
+		assert_eq!((report.summary.total, report.summary.errors), (4, 4));
+		assert_eq!(server.hits(), 3);
+		// Four starts, 50ms apart.
+		assert!(report.summary.elapsed >= Duration::from_millis(150));
+	}
+}
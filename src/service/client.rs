@@ -1,59 +1,256 @@
-use reqwest::blocking::{Request, Response};
+use std::time::{Duration, Instant};
+
+use reqwest::{
+	blocking::{Request, Response},
+	header::{HeaderValue, AUTHORIZATION},
+	Method,
+};
+use serde::de::DeserializeOwned;
 use url::Url;
 
-use super::pipeline::Pipeline;
+use super::{
+	endpoints::{EndpointStatus, Endpoints, Selection, DEFAULT_COOLDOWN},
+	pinning::TlsPinning,
+	pipeline::Pipeline,
+	signing_keys::SigningKeyCache,
+	token::{issuer_endpoint, verify_token, Jws},
+};
+use crate::{enclaves::model::PolicyResponse, utils::AttestationResult, AttestationReport};
 
 #[derive(Clone)]
 pub struct Client {
 	token: String,
-	endpoint: Url,
+	endpoints: Endpoints,
 	pipeline: Pipeline,
+	signing_keys: SigningKeyCache,
 }
 
 impl Client {
 	pub fn new(token: String, endpoint: Url) -> Self {
-		let pipeline = Pipeline::new();
-		Self { token, endpoint, pipeline }
+		let endpoints = Endpoints::new(vec![endpoint], Selection::Ordered, DEFAULT_COOLDOWN)
+			.expect("one endpoint");
+		Self::with_endpoints(token, endpoints, Pipeline::new(), SigningKeyCache::new())
+	}
+
+	pub(crate) fn with_endpoints(
+		token: String,
+		endpoints: Endpoints,
+		pipeline: Pipeline,
+		signing_keys: SigningKeyCache,
+	) -> Self {
+		Self { token, endpoints, pipeline, signing_keys }
 	}
 
+	// The primary endpoint, that request URLs are built from.
 	pub(crate) fn endpoint(&self) -> &Url {
-		&self.endpoint
+		self.endpoints.primary()
 	}
 
 	pub(crate) fn bearer_token(&self) -> &String {
 		&self.token
 	}
 
+	#[doc = "The health of each configured provider endpoint."]
+	pub fn endpoint_status(&self) -> Vec<EndpointStatus> {
+		self.endpoints.status()
+	}
+
 	pub fn attestation_client(&self) -> attestation::Client {
 		attestation::Client(self.clone())
 	}
 
+	pub fn policy_client(&self) -> policy::Client {
+		policy::Client(self.clone())
+	}
+
+	pub fn policy_certificates_client(&self) -> policy_certificates::Client {
+		policy_certificates::Client(self.clone())
+	}
+
 	pub(crate) fn send(&self, request: Request) -> Result<Response, String> {
 		self.pipeline.send(request)
 	}
+
+	// Send `request`, built for the primary endpoint, to the healthiest endpoint, failing over to
+	// the next one on transport errors and server errors. The outcome of the last endpoint tried
+	// is returned.
+	pub(crate) fn send_with_failover(&self, request: Request) -> Result<Response, String> {
+		let order = self.endpoints.order();
+		let mut outcome = Err("No attestation provider endpoint configured".to_string());
+		for (attempt, index) in order.iter().copied().enumerate() {
+			let mut req = match request.try_clone() {
+				Some(req) => req,
+				None if attempt == 0 => return self.send(request),
+				None => break,
+			};
+			*req.url_mut() = self.endpoints.rebase(request.url(), index)?;
+
+			let started = Instant::now();
+			outcome = self.send(req);
+			match &outcome {
+				Ok(response) if !response.status().is_server_error() => {
+					self.endpoints.record_success(index, started.elapsed());
+					break
+				},
+				_ => self.endpoints.record_failure(index),
+			}
+		}
+		outcome
+	}
+
+	// An authorized request for `path` relative to the endpoint, at the service API version.
+	pub(crate) fn request(&self, method: Method, path: &str) -> Result<Request, String> {
+		let mut url =
+			Url::parse(&format!("{}{}", self.endpoint(), path)).map_err(|e| e.to_string())?;
+		url.query_pairs_mut()
+			.append_pair(azure_core::query_param::API_VERSION, "2020-10-01");
+
+		let mut req = Request::new(method, url);
+		let bearer_token =
+			HeaderValue::from_str(&format!("Bearer {}", self.token)).map_err(|e| e.to_string())?;
+		req.headers_mut().insert(AUTHORIZATION, bearer_token);
+		Ok(req)
+	}
+
+	#[doc = "Verify the signature, issuer and lifetime of a token issued by any of the configured providers and decode its claims."]
+	pub fn verify_token(&self, token: &str) -> Result<AttestationResult, String> {
+		let endpoint = issuer_endpoint(token, self.endpoints.urls())?;
+		verify_token(token, endpoint, &self.signing_keys)
+	}
+
+	#[doc = "Check that tokens can be verified: that the signing keys of a provider are cached, or can be fetched."]
+	pub fn ready(&self) -> Result<(), String> {
+		let mut err = String::new();
+		for endpoint in self.endpoints.urls() {
+			if self.signing_keys.is_fresh(endpoint) {
+				return Ok(())
+			}
+			match self.signing_keys.refresh(endpoint) {
+				Ok(_) => return Ok(()),
+				Err(e) => err = e,
+			}
+		}
+		Err(err)
+	}
+
+	#[doc = "Verify a token like `verify_token`, and summarize it as a SCALE encodable `AttestationReport`."]
+	pub fn verify_report(&self, token: &str) -> Result<AttestationReport, String> {
+		let result = self.verify_token(token)?;
+		AttestationReport::from_verified(token, &result)
+	}
+}
+
+// Decode the claims of the token in a policy management response. They are not verified: they
+// describe the request just made over TLS, rather than being evidence to be relied upon later.
+pub(crate) fn into_token_claims<T: DeserializeOwned>(response: Response) -> Result<T, String> {
+	let status = response.status();
+	if !status.is_success() {
+		let body = response.text().unwrap_or_default();
+		return Err(format!("Request failed with {}: {}", status, body))
+	}
+
+	let response: PolicyResponse = response.json().map_err(|e| e.to_string())?;
+	let token = response.token.ok_or("Response has no token")?;
+	let jws = Jws::parse(&token)?;
+	serde_json::from_slice(&jws.claims).map_err(|e| e.to_string())
 }
 
 #[derive(Clone)]
 pub struct ClientBuilder {
 	token: String,
-	endpoint: Url,
+	endpoints: Vec<Url>,
+	selection: Selection,
+	cooldown: Duration,
+	signing_keys: Option<SigningKeyCache>,
+	tls_pinning: TlsPinning,
 }
 
 impl ClientBuilder {
 	#[doc = "Create a new instance of `ClientBuilder`."]
 	#[must_use]
 	pub fn new(token: String, endpoint: Url) -> Self {
-		Self { token, endpoint }
+		Self {
+			token,
+			endpoints: vec![endpoint],
+			selection: Selection::Ordered,
+			cooldown: DEFAULT_COOLDOWN,
+			signing_keys: None,
+			tls_pinning: TlsPinning::default(),
+		}
+	}
+
+	#[doc = "Fail attestation requests over to `endpoints`, in order, when the providers before them are unreachable or return server errors. Policy management stays on the first endpoint."]
+	#[must_use]
+	pub fn failover_endpoints(mut self, endpoints: impl IntoIterator<Item = Url>) -> Self {
+		self.endpoints.extend(endpoints);
+		self
+	}
+
+	#[doc = "Try the healthy endpoints fastest first rather than in order."]
+	#[must_use]
+	pub fn lowest_latency_first(mut self) -> Self {
+		self.selection = Selection::LowestLatency;
+		self
+	}
+
+	#[doc = "How long an endpoint that failed is tried only after the healthy ones."]
+	#[must_use]
+	pub fn cooldown(mut self, cooldown: Duration) -> Self {
+		self.cooldown = cooldown;
+		self
+	}
+
+	#[doc = "Share a signing key cache, e.g. between clients of a verification service."]
+	#[must_use]
+	pub fn signing_key_cache(mut self, signing_keys: SigningKeyCache) -> Self {
+		self.signing_keys = Some(signing_keys);
+		self
+	}
+
+	#[doc = "Check the TLS certificates of the providers against `pinning`, for requests and for fetching signing keys. A shared signing key cache keeps its own TLS settings."]
+	#[must_use]
+	pub fn tls_pinning(mut self, pinning: TlsPinning) -> Self {
+		self.tls_pinning = pinning;
+		self
 	}
 
 	#[doc = "Convert the builder into a `Client` instance."]
 	pub fn build(self) -> Result<Client, String> {
-		Ok(Client::new(self.token, self.endpoint))
+		let signing_keys = match self.signing_keys {
+			Some(signing_keys) => signing_keys,
+			None => SigningKeyCache::new().with_tls_pinning(&self.tls_pinning)?,
+		};
+		let endpoints = Endpoints::new(self.endpoints, self.selection, self.cooldown)?;
+		let pipeline = Pipeline::with_tls_pinning(&self.tls_pinning)?;
+		Ok(Client::with_endpoints(self.token, endpoints, pipeline, signing_keys))
 	}
 }
 
 pub mod attestation {
-	use crate::enclaves::model::{AttestOpenEnclaveRequest, AttestSgxEnclaveRequest};
+	use reqwest::blocking::Response;
+
+	use crate::{
+		enclaves::model::{
+			AttestOpenEnclaveRequest, AttestSevSnpVmRequest, AttestSgxEnclaveRequest,
+			AttestationResponse,
+		},
+		service::error::AttestError,
+	};
+
+	// The token of an attest response.
+	fn into_token(response: Response) -> Result<String, AttestError> {
+		let status = response.status();
+		if !status.is_success() {
+			let body = response.text().unwrap_or_default();
+			return Err(AttestError::Status { code: status.as_u16(), body })
+		}
+
+		let response: AttestationResponse =
+			response.json().map_err(|e| AttestError::Request(e.to_string()))?;
+		response
+			.token
+			.ok_or(AttestError::Request("Attestation response has no token".into()))
+	}
 
 	pub struct Client(pub(crate) super::Client);
 	impl Client {
@@ -72,10 +269,22 @@ pub mod attestation {
 		) -> attest_sgx_enclave::RequestBuilder {
 			attest_sgx_enclave::RequestBuilder { client: self.0.clone(), request: request.into() }
 		}
+
+		#[doc = "Attest to an SEV-SNP confidential VM."]
+		pub fn attest_sev_snp_vm(
+			&self,
+			request: impl Into<AttestSevSnpVmRequest>,
+		) -> attest_sev_snp_vm::RequestBuilder {
+			attest_sev_snp_vm::RequestBuilder { client: self.0.clone(), request: request.into() }
+		}
 	}
 
 	pub mod attest_open_enclave {
-		use crate::{enclaves::model::AttestOpenEnclaveRequest, service::to_json};
+		use crate::{
+			enclaves::model::{AttestOpenEnclaveRequest, InitTimeData},
+			service::{error::AttestError, nonce, to_json},
+			utils::AttestationResult,
+		};
 		use reqwest::blocking::{Request, Response};
 		use url::Url;
 
@@ -86,6 +295,42 @@ pub mod attestation {
 			pub(crate) request: AttestOpenEnclaveRequest,
 		}
 		impl RequestBuilder {
+			#[doc = "Attest against `policy` instead of the policy set on the provider."]
+			pub fn with_draft_policy(
+				mut self,
+				policy: impl Into<String>,
+			) -> super::draft::RequestBuilder {
+				self.request.draft_policy_for_attestation = Some(policy.into());
+				super::draft::RequestBuilder::OpenEnclave(self)
+			}
+
+			#[doc = "Attest with `init_time_data`, whose hash the TEE must carry in its CONFIGID."]
+			pub fn with_init_time_data(mut self, init_time_data: InitTimeData) -> Self {
+				self.request.init_time_data = Some(init_time_data);
+				self
+			}
+
+			#[doc = "Attest with `nonce`, which the token must then carry."]
+			pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+				self.request.nonce = Some(nonce.into());
+				self
+			}
+
+			#[doc = "Attest and verify the returned token, including its nonce. A fresh nonce is generated unless one was set."]
+			pub fn into_result(mut self) -> Result<AttestationResult, AttestError> {
+				let nonce = match self.request.nonce.clone() {
+					Some(nonce) => nonce,
+					None => nonce::generate().map_err(AttestError::Request)?,
+				};
+				self.request.nonce = Some(nonce.clone());
+
+				let client = self.client.clone();
+				let token = super::into_token(self.send().map_err(AttestError::Request)?)?;
+				let result = client.verify_token(&token).map_err(AttestError::Token)?;
+				nonce::check(&result, &nonce).map_err(AttestError::Token)?;
+				Ok(result)
+			}
+
 			pub fn send(self) -> Result<Response, String> {
 				let url = self.url().unwrap();
 
@@ -106,7 +351,7 @@ pub mod attestation {
 				let req_body = to_json(&self.request).unwrap();
 				*req.body_mut() = Some(req_body.into());
 
-				self.client.send(req)
+				self.client.send_with_failover(req)
 			}
 
 			fn url(&self) -> Result<Url, String> {
@@ -126,7 +371,11 @@ pub mod attestation {
 		use reqwest::blocking::{Request, Response};
 		use url::Url;
 
-		use crate::{enclaves::model::AttestSgxEnclaveRequest, service::to_json};
+		use crate::{
+			enclaves::model::{AttestSgxEnclaveRequest, InitTimeData},
+			service::{error::AttestError, nonce, to_json},
+			utils::AttestationResult,
+		};
 
 		#[derive(Clone)]
 		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
@@ -135,6 +384,42 @@ pub mod attestation {
 			pub(crate) request: AttestSgxEnclaveRequest,
 		}
 		impl RequestBuilder {
+			#[doc = "Attest against `policy` instead of the policy set on the provider."]
+			pub fn with_draft_policy(
+				mut self,
+				policy: impl Into<String>,
+			) -> super::draft::RequestBuilder {
+				self.request.draft_policy_for_attestation = Some(policy.into());
+				super::draft::RequestBuilder::SgxEnclave(self)
+			}
+
+			#[doc = "Attest with `init_time_data`, whose hash the TEE must carry in its CONFIGID."]
+			pub fn with_init_time_data(mut self, init_time_data: InitTimeData) -> Self {
+				self.request.init_time_data = Some(init_time_data);
+				self
+			}
+
+			#[doc = "Attest with `nonce`, which the token must then carry."]
+			pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+				self.request.nonce = Some(nonce.into());
+				self
+			}
+
+			#[doc = "Attest and verify the returned token, including its nonce. A fresh nonce is generated unless one was set."]
+			pub fn into_result(mut self) -> Result<AttestationResult, AttestError> {
+				let nonce = match self.request.nonce.clone() {
+					Some(nonce) => nonce,
+					None => nonce::generate().map_err(AttestError::Request)?,
+				};
+				self.request.nonce = Some(nonce.clone());
+
+				let client = self.client.clone();
+				let token = super::into_token(self.send().map_err(AttestError::Request)?)?;
+				let result = client.verify_token(&token).map_err(AttestError::Token)?;
+				nonce::check(&result, &nonce).map_err(AttestError::Token)?;
+				Ok(result)
+			}
+
 			pub fn send(self) -> Result<Response, String> {
 				let url = self.url()?;
 
@@ -155,7 +440,7 @@ pub mod attestation {
 				let req_body = to_json(&self.request).unwrap();
 				*req.body_mut() = Some(req_body.into());
 
-				self.client.send(req)
+				self.client.send_with_failover(req)
 			}
 			fn url(&self) -> Result<Url, String> {
 				let mut url =
@@ -170,4 +455,587 @@ pub mod attestation {
 			}
 		}
 	}
+
+	pub mod attest_sev_snp_vm {
+		use reqwest::blocking::{Request, Response};
+		use url::Url;
+
+		use crate::{
+			enclaves::model::{AttestSevSnpVmRequest, InitTimeData},
+			service::{error::AttestError, nonce, to_json},
+			utils::AttestationResult,
+		};
+
+		#[derive(Clone)]
+		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
+		pub struct RequestBuilder {
+			pub(crate) client: super::super::Client,
+			pub(crate) request: AttestSevSnpVmRequest,
+		}
+		impl RequestBuilder {
+			#[doc = "Attest against `policy` instead of the policy set on the provider."]
+			pub fn with_draft_policy(
+				mut self,
+				policy: impl Into<String>,
+			) -> super::draft::RequestBuilder {
+				self.request.draft_policy_for_attestation = Some(policy.into());
+				super::draft::RequestBuilder::SevSnpVm(self)
+			}
+
+			#[doc = "Attest with `init_time_data`, whose hash the VM must carry in its HOST_DATA."]
+			pub fn with_init_time_data(mut self, init_time_data: InitTimeData) -> Self {
+				self.request.init_time_data = Some(init_time_data);
+				self
+			}
+
+			#[doc = "Attest with `nonce`, which the token must then carry."]
+			pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+				self.request.nonce = Some(nonce.into());
+				self
+			}
+
+			#[doc = "Attest and verify the returned token, including its nonce. A fresh nonce is generated unless one was set."]
+			pub fn into_result(mut self) -> Result<AttestationResult, AttestError> {
+				let nonce = match self.request.nonce.clone() {
+					Some(nonce) => nonce,
+					None => nonce::generate().map_err(AttestError::Request)?,
+				};
+				self.request.nonce = Some(nonce.clone());
+
+				let client = self.client.clone();
+				let token = super::into_token(self.send().map_err(AttestError::Request)?)?;
+				let result = client.verify_token(&token).map_err(AttestError::Token)?;
+				nonce::check(&result, &nonce).map_err(AttestError::Token)?;
+				Ok(result)
+			}
+
+			pub fn send(self) -> Result<Response, String> {
+				let url = self.url()?;
+
+				let mut req = Request::new(reqwest::Method::POST, url);
+				let headers = req.headers_mut();
+
+				let bearer_token = self.client.bearer_token();
+				headers.insert(
+					reqwest::header::AUTHORIZATION,
+					reqwest::header::HeaderValue::from_str(&format!("Bearer {}", bearer_token))
+						.map_err(|e| e.to_string())?,
+				);
+				headers.insert(
+					"content-type",
+					reqwest::header::HeaderValue::from_static("application/json"),
+				);
+
+				let req_body = to_json(&self.request).map_err(|e| e.to_string())?;
+				*req.body_mut() = Some(req_body.into());
+
+				self.client.send_with_failover(req)
+			}
+
+			// SEV-SNP attestation is only available from the 2022-08-01 API version on.
+			fn url(&self) -> Result<Url, String> {
+				let mut url = Url::parse(&format!("{}attest/SevSnpVm", self.client.endpoint()))
+					.map_err(|e| e.to_string())?;
+				let has_api_version_already =
+					url.query_pairs().any(|(k, _)| k == azure_core::query_param::API_VERSION);
+				if !has_api_version_already {
+					url.query_pairs_mut()
+						.append_pair(azure_core::query_param::API_VERSION, "2022-08-01");
+				}
+				Ok(url)
+			}
+		}
+	}
+
+	pub mod draft {
+		use reqwest::blocking::Response;
+
+		use crate::utils::DraftAttestationResult;
+
+		#[derive(Clone)]
+		#[doc = r" An attestation against a draft policy, whose result is unsigned and kept apart from verified results."]
+		pub enum RequestBuilder {
+			SgxEnclave(super::attest_sgx_enclave::RequestBuilder),
+			OpenEnclave(super::attest_open_enclave::RequestBuilder),
+			SevSnpVm(super::attest_sev_snp_vm::RequestBuilder),
+		}
+		impl RequestBuilder {
+			pub fn send(self) -> Result<Response, String> {
+				match self {
+					RequestBuilder::SgxEnclave(builder) => builder.send(),
+					RequestBuilder::OpenEnclave(builder) => builder.send(),
+					RequestBuilder::SevSnpVm(builder) => builder.send(),
+				}
+			}
+
+			pub fn into_result(self) -> Result<DraftAttestationResult, String> {
+				let token = super::into_token(self.send()?)?;
+				DraftAttestationResult::from_token(token)
+			}
+		}
+	}
+}
+
+pub mod policy {
+	use crate::enclaves::model::{AttestationPolicy, AttestationType};
+
+	pub struct Client(pub(crate) super::Client);
+	impl Client {
+		#[doc = "Retrieve the current policy for an attestation type."]
+		pub fn get(&self, attestation_type: AttestationType) -> get::RequestBuilder {
+			get::RequestBuilder { client: self.0.clone(), attestation_type }
+		}
+
+		#[doc = "Set the policy for an attestation type."]
+		pub fn set(
+			&self,
+			attestation_type: AttestationType,
+			policy: AttestationPolicy,
+		) -> set::RequestBuilder {
+			set::RequestBuilder { client: self.0.clone(), attestation_type, policy }
+		}
+
+		#[doc = "Reset the policy for an attestation type to the service default."]
+		pub fn reset(&self, attestation_type: AttestationType) -> reset::RequestBuilder {
+			reset::RequestBuilder { client: self.0.clone(), attestation_type, signed_jws: None }
+		}
+	}
+
+	pub mod get {
+		use reqwest::blocking::Response;
+
+		use crate::enclaves::model::{AttestationType, PolicyResult};
+
+		#[derive(Clone)]
+		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
+		pub struct RequestBuilder {
+			pub(crate) client: super::super::Client,
+			pub(crate) attestation_type: AttestationType,
+		}
+		impl RequestBuilder {
+			pub fn send(self) -> Result<Response, String> {
+				let path = format!("policies/{}", self.attestation_type);
+				let req = self.client.request(reqwest::Method::GET, &path)?;
+				self.client.send(req)
+			}
+
+			pub fn into_result(self) -> Result<PolicyResult, String> {
+				super::super::into_token_claims(self.send()?)
+			}
+		}
+	}
+
+	pub mod set {
+		use reqwest::blocking::Response;
+
+		use crate::{
+			enclaves::model::{
+				AttestationPolicy, AttestationType, PolicyResult, StoredAttestationPolicy,
+			},
+			service::token::unsigned_jws,
+			utils::base64url,
+		};
+
+		#[derive(Clone)]
+		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
+		pub struct RequestBuilder {
+			pub(crate) client: super::super::Client,
+			pub(crate) attestation_type: AttestationType,
+			pub(crate) policy: AttestationPolicy,
+		}
+		impl RequestBuilder {
+			pub fn send(self) -> Result<Response, String> {
+				let body = match self.policy {
+					AttestationPolicy::Text(text) => {
+						let stored = StoredAttestationPolicy {
+							attestation_policy: Some(base64url(text.as_bytes())),
+						};
+						unsigned_jws(&serde_json::to_vec(&stored).map_err(|e| e.to_string())?)
+					},
+					AttestationPolicy::SignedJws(jws) => jws,
+				};
+
+				let path = format!("policies/{}", self.attestation_type);
+				let mut req = self.client.request(reqwest::Method::PUT, &path)?;
+				req.headers_mut().insert(
+					"content-type",
+					reqwest::header::HeaderValue::from_static("text/plain"),
+				);
+				*req.body_mut() = Some(body.into());
+
+				self.client.send(req)
+			}
+
+			pub fn into_result(self) -> Result<PolicyResult, String> {
+				super::super::into_token_claims(self.send()?)
+			}
+		}
+	}
+
+	pub mod reset {
+		use reqwest::blocking::Response;
+
+		use crate::{
+			enclaves::model::{AttestationType, PolicyResult},
+			service::token::unsigned_jws,
+		};
+
+		#[derive(Clone)]
+		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
+		pub struct RequestBuilder {
+			pub(crate) client: super::super::Client,
+			pub(crate) attestation_type: AttestationType,
+			pub(crate) signed_jws: Option<String>,
+		}
+		impl RequestBuilder {
+			#[doc = "Authorize the reset with a JWS with an empty payload, signed by a policy management key. Required for isolated mode providers."]
+			pub fn signed_jws(mut self, jws: String) -> Self {
+				self.signed_jws = Some(jws);
+				self
+			}
+
+			pub fn send(self) -> Result<Response, String> {
+				let body = self.signed_jws.unwrap_or_else(|| unsigned_jws(b""));
+
+				let path = format!("policies/{}:reset", self.attestation_type);
+				let mut req = self.client.request(reqwest::Method::POST, &path)?;
+				req.headers_mut().insert(
+					"content-type",
+					reqwest::header::HeaderValue::from_static("text/plain"),
+				);
+				*req.body_mut() = Some(body.into());
+
+				self.client.send(req)
+			}
+
+			pub fn into_result(self) -> Result<PolicyResult, String> {
+				super::super::into_token_claims(self.send()?)
+			}
+		}
+	}
+}
+
+pub mod policy_certificates {
+	pub struct Client(pub(crate) super::Client);
+	impl Client {
+		#[doc = "Retrieve the certificates trusted to sign policies and policy management requests."]
+		pub fn list(&self) -> list::RequestBuilder {
+			list::RequestBuilder { client: self.0.clone() }
+		}
+
+		#[doc = "Add a policy management certificate. `signed_jws` wraps a `PolicyCertificateBody` and is signed by an existing policy management key."]
+		pub fn add(&self, signed_jws: String) -> modify::RequestBuilder {
+			modify::RequestBuilder { client: self.0.clone(), operation: "add", signed_jws }
+		}
+
+		#[doc = "Remove a policy management certificate. `signed_jws` wraps a `PolicyCertificateBody` and is signed by an existing policy management key."]
+		pub fn remove(&self, signed_jws: String) -> modify::RequestBuilder {
+			modify::RequestBuilder { client: self.0.clone(), operation: "remove", signed_jws }
+		}
+	}
+
+	pub mod list {
+		use reqwest::blocking::Response;
+
+		use crate::enclaves::model::PolicyCertificatesResult;
+
+		#[derive(Clone)]
+		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
+		pub struct RequestBuilder {
+			pub(crate) client: super::super::Client,
+		}
+		impl RequestBuilder {
+			pub fn send(self) -> Result<Response, String> {
+				let req = self.client.request(reqwest::Method::GET, "certificates")?;
+				self.client.send(req)
+			}
+
+			pub fn into_result(self) -> Result<PolicyCertificatesResult, String> {
+				super::super::into_token_claims(self.send()?)
+			}
+		}
+	}
+
+	pub mod modify {
+		use reqwest::blocking::Response;
+
+		use crate::enclaves::model::PolicyCertificatesModificationResult;
+
+		#[derive(Clone)]
+		#[doc = r" `RequestBuilder` provides a mechanism for setting optional parameters on a request."]
+		pub struct RequestBuilder {
+			pub(crate) client: super::super::Client,
+			pub(crate) operation: &'static str,
+			pub(crate) signed_jws: String,
+		}
+		impl RequestBuilder {
+			pub fn send(self) -> Result<Response, String> {
+				let path = format!("certificates:{}", self.operation);
+				let mut req = self.client.request(reqwest::Method::POST, &path)?;
+				req.headers_mut().insert(
+					"content-type",
+					reqwest::header::HeaderValue::from_static("text/plain"),
+				);
+				*req.body_mut() = Some(self.signed_jws.into());
+
+				self.client.send(req)
+			}
+
+			pub fn into_result(self) -> Result<PolicyCertificatesModificationResult, String> {
+				super::super::into_token_claims(self.send()?)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::{
+		enclaves::{
+			evidence::Evidence,
+			model::{
+				AttestSgxEnclaveRequest, AttestationPolicy, AttestationType,
+				CertificateModification, PolicyModification,
+			},
+		},
+		service::{
+			error::AttestError,
+			stand_in::{SigningStandIn, StandIn},
+			token::unsigned_jws,
+		},
+		utils::base64url,
+	};
+
+	const POLICY: &str = "version=1.0; authorizationrules{=> permit();};";
+
+	fn policy_response(claims: serde_json::Value) -> String {
+		serde_json::json!({ "token": unsigned_jws(claims.to_string().as_bytes()) }).to_string()
+	}
+
+	#[test]
+	fn policy_get_set_reset_works() {
+		let stored = serde_json::json!({ "AttestationPolicy": base64url(POLICY.as_bytes()) });
+		let server = StandIn::serve(vec![
+			(
+				"GET /policies/SgxEnclave",
+				200,
+				policy_response(serde_json::json!({
+					"x-ms-policy": unsigned_jws(stored.to_string().as_bytes()),
+				})),
+			),
+			(
+				"PUT /policies/SgxEnclave",
+				200,
+				policy_response(serde_json::json!({
+					"x-ms-policy-result": "Updated",
+					"x-ms-policy-token-hash": "hash",
+				})),
+			),
+			(
+				"POST /policies/SgxEnclave:reset",
+				200,
+				policy_response(serde_json::json!({ "x-ms-policy-result": "Removed" })),
+			),
+		]);
+		let policy_client = ClientBuilder::new("token".into(), server.url())
+			.build()
+			.unwrap()
+			.policy_client();
+
+		let current = policy_client.get(AttestationType::SgxEnclave).into_result().unwrap();
+		assert_eq!(current.policy_text().unwrap().as_deref(), Some(POLICY));
+
+		let updated = policy_client
+			.set(AttestationType::SgxEnclave, AttestationPolicy::Text(POLICY.into()))
+			.into_result()
+			.unwrap();
+		assert_eq!(updated.policy_resolution, Some(PolicyModification::Updated));
+		assert_eq!(updated.policy_token_hash.as_deref(), Some("hash"));
+
+		let reset = policy_client.reset(AttestationType::SgxEnclave).into_result().unwrap();
+		assert_eq!(reset.policy_resolution, Some(PolicyModification::Removed));
+
+		let requests = server.requests();
+		assert!(requests[0].path.ends_with("?api-version=2020-10-01"));
+		assert_eq!(requests[1].body, unsigned_jws(stored.to_string().as_bytes()));
+		assert_eq!(requests[2].body, "eyJhbGciOiJub25lIn0..");
+	}
+
+	#[test]
+	fn policy_errors_are_reported() {
+		let server = StandIn::serve(vec![]);
+		let policy_client = ClientBuilder::new("token".into(), server.url())
+			.build()
+			.unwrap()
+			.policy_client();
+
+		let err = policy_client.get(AttestationType::Tpm).into_result().unwrap_err();
+		assert!(err.contains("404"));
+	}
+
+	#[test]
+	fn policy_certificates_list_add_remove_works() {
+		let certificates = serde_json::json!({
+			"x-ms-policy-certificates": { "keys": [{ "kty": "RSA", "x5c": ["AAEC"] }] },
+		});
+		let server = StandIn::serve(vec![
+			("GET /certificates", 200, policy_response(certificates)),
+			(
+				"POST /certificates:add",
+				200,
+				policy_response(serde_json::json!({
+					"x-ms-certificate-thumbprint": "2A2D4D8E4C4A04E4A5C6D8E1A1C9C1B3E0C0A4A4",
+					"x-ms-policycertificates-result": "IsPresent",
+				})),
+			),
+			(
+				"POST /certificates:remove",
+				200,
+				policy_response(
+					serde_json::json!({ "x-ms-policycertificates-result": "IsAbsent" }),
+				),
+			),
+		]);
+		let certificates_client = ClientBuilder::new("token".into(), server.url())
+			.build()
+			.unwrap()
+			.policy_certificates_client();
+
+		let listed = certificates_client.list().into_result().unwrap();
+		let keys = listed.policy_certificates.unwrap().keys;
+		assert_eq!(
+			keys[0].thumbprint().unwrap().as_deref(),
+			Some("0C7A623FD2BBC05B06423BE359E4021D36E721AD")
+		);
+
+		let added = certificates_client.add("signed.add.jws".into()).into_result().unwrap();
+		assert_eq!(added.certificate_resolution, Some(CertificateModification::IsPresent));
+		assert!(added.certificate_thumbprint.is_some());
+
+		let removed = certificates_client.remove("signed.remove.jws".into()).into_result().unwrap();
+		assert_eq!(removed.certificate_resolution, Some(CertificateModification::IsAbsent));
+
+		let requests = server.requests();
+		assert_eq!(requests[1].body, "signed.add.jws");
+		assert!(requests[2].path.starts_with("/certificates:remove?"));
+	}
+
+	#[test]
+	fn draft_policy_attestation_is_unverified() {
+		let server = StandIn::serve(vec![(
+			"POST /attest/SgxEnclave",
+			200,
+			policy_response(serde_json::json!({ "exp": 2, "x-ms-sgx-svn": 8888, "draft": true })),
+		)]);
+		let attestation_client = ClientBuilder::new("token".into(), server.url())
+			.build()
+			.unwrap()
+			.attestation_client();
+
+		let mut request = AttestSgxEnclaveRequest::new();
+		request.quote = Some("AAEC".into());
+		let draft = attestation_client
+			.attest_sgx_enclave(request)
+			.with_draft_policy(POLICY)
+			.into_result()
+			.unwrap();
+		assert_eq!(draft.claims()["draft"], serde_json::json!(true));
+
+		let current = serde_json::json!({ "exp": 1, "x-ms-sgx-svn": 1, "current": true });
+		let changes: Vec<String> =
+			draft.diff(current.as_object().unwrap()).iter().map(|c| c.to_string()).collect();
+		assert_eq!(changes, ["- current: true", "~ x-ms-sgx-svn: 1 -> 8888", "+ draft: true"]);
+
+		let body: serde_json::Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+		assert_eq!(body["draftPolicyForAttestation"], POLICY);
+	}
+
+	#[test]
+	fn attestation_errors_tell_rejections_apart() {
+		let mut request = AttestSgxEnclaveRequest::new();
+		request.quote = Some("AAEC".into());
+		let attest = |server: &StandIn| {
+			ClientBuilder::new("token".into(), server.url())
+				.build()
+				.unwrap()
+				.attestation_client()
+				.attest_sgx_enclave(request.clone())
+				.into_result()
+		};
+
+		let rejecting = StandIn::serve(vec![("POST /attest/SgxEnclave", 400, "denied".into())]);
+		let err = attest(&rejecting).unwrap_err();
+		assert_eq!(err, AttestError::Status { code: 400, body: "denied".into() });
+		assert_eq!(err.to_string(), "Attestation failed with 400: denied");
+
+		// An unsigned token does not verify.
+		let unsigned = StandIn::serve(vec![(
+			"POST /attest/SgxEnclave",
+			200,
+			policy_response(serde_json::json!({ "x-ms-sgx-svn": 8888 })),
+		)]);
+		assert!(matches!(attest(&unsigned), Err(AttestError::Token(_))));
+
+		let malformed = StandIn::serve(vec![("POST /attest/SgxEnclave", 200, "{}".into())]);
+		assert!(matches!(attest(&malformed), Err(AttestError::Request(_))));
+	}
+
+	#[test]
+	fn tokens_for_another_nonce_are_refused() {
+		let provider = SigningStandIn::serve();
+		let token =
+			provider.token(serde_json::json!({ "nonce": "replayed", "x-ms-sgx-svn": 8888 }));
+		provider.stand_in.respond_with("POST /attest/", move |_| {
+			(200, serde_json::json!({ "token": token }).to_string())
+		});
+		let client = ClientBuilder::new("token".into(), provider.url())
+			.build()
+			.unwrap()
+			.attestation_client();
+
+		let evidence = Evidence::from_bytes(&[0, 1, 2]).unwrap();
+		let refused = [
+			client
+				.attest_sgx_enclave(evidence.sgx_request())
+				.with_nonce("fresh")
+				.into_result(),
+			client.attest_open_enclave(evidence.open_enclave_request()).into_result(),
+			client.attest_sev_snp_vm(evidence.sev_snp_request()).into_result(),
+		];
+		for result in refused {
+			match result {
+				Err(AttestError::Token(err)) => assert!(err.contains("replayed"), "{}", err),
+				other => panic!("{:?}", other.map(|result| result.nonce)),
+			}
+		}
+	}
+
+	#[test]
+	fn attestation_fails_over_to_healthy_endpoints() {
+		let unreachable = {
+			let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+			Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap()
+		};
+		let erroring = StandIn::serve(vec![("POST /attest/SgxEnclave", 503, "{}".into())]);
+		let rejecting = StandIn::serve(vec![("POST /attest/SgxEnclave", 400, "{}".into())]);
+		let client = ClientBuilder::new("token".into(), unreachable)
+			.failover_endpoints([erroring.url(), rejecting.url()])
+			.build()
+			.unwrap();
+
+		let mut request = AttestSgxEnclaveRequest::new();
+		request.quote = Some("AAEC".into());
+		let attest = || client.attestation_client().attest_sgx_enclave(request.clone()).send();
+
+		assert_eq!(attest().unwrap().status(), 400);
+		assert_eq!((erroring.hits(), rejecting.hits()), (1, 1));
+		let healthy: Vec<bool> = client.endpoint_status().iter().map(|s| s.healthy).collect();
+		assert_eq!(healthy, [false, false, true]);
+
+		// Endpoints that failed are only tried once the healthy ones failed too.
+		assert_eq!(attest().unwrap().status(), 400);
+		assert_eq!((erroring.hits(), rejecting.hits()), (1, 2));
+		assert!(rejecting.requests()[1].path.starts_with("/attest/SgxEnclave?api-version="));
+	}
 }
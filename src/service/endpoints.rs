@@ -0,0 +1,180 @@
+//! An ordered set of attestation provider endpoints, with the health of each, for failing over
+//! when a provider is unreachable or erroring.
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use url::Url;
+
+/// How long an endpoint that failed is tried only after the healthy ones.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Selection {
+	/// Try healthy endpoints in the configured order.
+	#[default]
+	Ordered,
+	/// Try healthy endpoints fastest first, by their recent response times. Endpoints not yet
+	/// measured are tried first, so that each gets measured.
+	LowestLatency,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndpointStatus {
+	pub url: Url,
+	pub healthy: bool,
+	/// Consecutive failures since the last success.
+	pub failures: u32,
+	/// Smoothed response time.
+	pub latency: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+	failures: u32,
+	unhealthy_until: Option<Instant>,
+	latency: Option<Duration>,
+}
+
+impl Health {
+	fn is_healthy(&self, now: Instant) -> bool {
+		match self.unhealthy_until {
+			Some(until) => until <= now,
+			None => true,
+		}
+	}
+}
+
+/// Endpoints of equivalent providers, most preferred first. Clones share the health of the
+/// endpoints.
+#[derive(Clone, Debug)]
+pub struct Endpoints {
+	urls: Vec<Url>,
+	health: Arc<Mutex<Vec<Health>>>,
+	selection: Selection,
+	cooldown: Duration,
+}
+
+impl Endpoints {
+	pub fn new(urls: Vec<Url>, selection: Selection, cooldown: Duration) -> Result<Self, String> {
+		if urls.is_empty() {
+			return Err("No attestation provider endpoint configured".into())
+		}
+		let health = urls.iter().map(|_| Health::default()).collect();
+		Ok(Self { urls, health: Arc::new(Mutex::new(health)), selection, cooldown })
+	}
+
+	/// The first configured endpoint.
+	pub fn primary(&self) -> &Url {
+		&self.urls[0]
+	}
+
+	pub fn urls(&self) -> &[Url] {
+		&self.urls
+	}
+
+	/// Indices of the endpoints in the order to try them: healthy ones by the selection, then
+	/// those cooling down, soonest healthy first.
+	pub(crate) fn order(&self) -> Vec<usize> {
+		let now = Instant::now();
+		let health = self.health.lock().unwrap();
+		let (mut healthy, mut cooling): (Vec<usize>, Vec<usize>) =
+			(0..self.urls.len()).partition(|i| health[*i].is_healthy(now));
+
+		if self.selection == Selection::LowestLatency {
+			healthy.sort_by_key(|i| health[*i].latency.unwrap_or_default());
+		}
+		cooling.sort_by_key(|i| health[*i].unhealthy_until);
+		healthy.extend(cooling);
+		healthy
+	}
+
+	pub(crate) fn record_success(&self, index: usize, latency: Duration) {
+		let mut health = self.health.lock().unwrap();
+		let health = &mut health[index];
+		health.failures = 0;
+		health.unhealthy_until = None;
+		health.latency = Some(match health.latency {
+			Some(average) => (average * 3 + latency) / 4,
+			None => latency,
+		});
+	}
+
+	pub(crate) fn record_failure(&self, index: usize) {
+		let mut health = self.health.lock().unwrap();
+		let health = &mut health[index];
+		health.failures += 1;
+		health.unhealthy_until = Some(Instant::now() + self.cooldown);
+	}
+
+	pub fn status(&self) -> Vec<EndpointStatus> {
+		let now = Instant::now();
+		let health = self.health.lock().unwrap();
+		self.urls
+			.iter()
+			.zip(health.iter())
+			.map(|(url, health)| EndpointStatus {
+				url: url.clone(),
+				healthy: health.is_healthy(now),
+				failures: health.failures,
+				latency: health.latency,
+			})
+			.collect()
+	}
+
+	/// `url`, built relative to the primary endpoint, relative to endpoint `index` instead.
+	pub(crate) fn rebase(&self, url: &Url, index: usize) -> Result<Url, String> {
+		let path = url
+			.as_str()
+			.strip_prefix(self.primary().as_str())
+			.ok_or(format!("{} is not a provider URL", url))?;
+		Url::parse(&format!("{}{}", self.urls[index], path)).map_err(|e| e.to_string())
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	fn endpoints(selection: Selection) -> Endpoints {
+		let urls = [
+			"https://eus.attest.azure.net",
+			"https://weu.attest.azure.net",
+			"https://jpe.attest.azure.net",
+		];
+		Endpoints::new(
+			urls.iter().map(|url| Url::parse(url).unwrap()).collect(),
+			selection,
+			DEFAULT_COOLDOWN,
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn failed_endpoints_are_tried_last() {
+		let ordered = endpoints(Selection::Ordered);
+		assert_eq!(ordered.order(), [0, 1, 2]);
+		ordered.record_failure(0);
+		ordered.record_failure(1);
+		assert_eq!(ordered.order(), [2, 0, 1]);
+		ordered.record_success(0, Duration::from_millis(10));
+		assert_eq!(ordered.order(), [0, 2, 1]);
+		assert_eq!(ordered.status()[1].failures, 1);
+
+		let fastest = endpoints(Selection::LowestLatency);
+		fastest.record_success(0, Duration::from_millis(80));
+		fastest.record_success(1, Duration::from_millis(20));
+		assert_eq!(fastest.order(), [2, 1, 0]);
+
+		let url =
+			Url::parse("https://eus.attest.azure.net/attest/SgxEnclave?api-version=2020-10-01")
+				.unwrap();
+		assert_eq!(
+			ordered.rebase(&url, 1).unwrap().as_str(),
+			"https://weu.attest.azure.net/attest/SgxEnclave?api-version=2020-10-01"
+		);
+		assert!(Endpoints::new(vec![], Selection::Ordered, DEFAULT_COOLDOWN).is_err());
+	}
+}
@@ -0,0 +1,76 @@
+use std::{
+	fmt,
+	string::{String, ToString},
+};
+
+/// Errors of an attestation through `MAAService`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MAAError {
+	/// The provider endpoint is not a valid URL.
+	InvalidEndpoint(String),
+	/// The provider could not be reached.
+	Connect(String),
+	/// A TLS root is invalid, or the TLS handshake failed.
+	Tls(String),
+	/// Sending the request or reading the response failed.
+	Http(String),
+	/// The provider answered with an unsuccessful status.
+	Status { code: u16, reason: String, body: String },
+	/// The response body is not a valid chunked transfer encoding.
+	Chunked(String),
+	/// The response is not an attestation response carrying a JSON Web Token.
+	Response(String),
+}
+
+impl fmt::Display for MAAError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			MAAError::InvalidEndpoint(err) => write!(f, "Invalid attestation endpoint: {}", err),
+			MAAError::Connect(err) => write!(f, "Failed to connect to the provider: {}", err),
+			MAAError::Tls(err) => write!(f, "TLS error: {}", err),
+			MAAError::Http(err) => write!(f, "HTTP error: {}", err),
+			MAAError::Status { code, reason, body } =>
+				write!(f, "Attestation failed with {} {}: {}", code, reason, body),
+			MAAError::Chunked(err) => write!(f, "Invalid chunked response: {}", err),
+			MAAError::Response(err) => write!(f, "Invalid attestation response: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for MAAError {}
+
+impl From<MAAError> for String {
+	fn from(err: MAAError) -> String {
+		err.to_string()
+	}
+}
+
+/// Errors of an attestation through the attestation `Client`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttestError {
+	/// The provider answered with an unsuccessful status, a 400 when it rejects the evidence.
+	Status { code: u16, body: String },
+	/// The returned token failed verification: its signature, issuer, lifetime or nonce.
+	Token(String),
+	/// Sending the request or reading the response failed.
+	Request(String),
+}
+
+impl fmt::Display for AttestError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			AttestError::Status { code, body } =>
+				write!(f, "Attestation failed with {}: {}", code, body),
+			AttestError::Token(err) => write!(f, "Token verification failed: {}", err),
+			AttestError::Request(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl std::error::Error for AttestError {}
+
+impl From<AttestError> for String {
+	fn from(err: AttestError) -> String {
+		err.to_string()
+	}
+}
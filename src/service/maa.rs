@@ -1,133 +1,228 @@
-use crate::{config::Config, utils::base64};
-use azure_core::base64;
-use codec::{Decode, Encode};
+use super::{error::MAAError, pinning::TlsPinning};
+use crate::{
+	config::Config,
+	utils::{base64, base64url_decode},
+};
 use http_req::{
 	request::{Method, RequestBuilder},
 	tls,
 	uri::Uri,
 };
-use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::{
+	io::{Read, Write},
 	net::TcpStream,
 	string::{String, ToString},
+	sync::Arc,
 	vec::Vec,
 };
 
-type EnclaveResult<T> = Result<T, String>;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MAAPolicy {
-	#[serde(rename = "is-debuggable")]
-	pub is_debuggable: bool,
-
-	#[serde(rename = "product-id")]
-	pub product_id: u32,
-
-	#[serde(rename = "sgx-mrenclave")]
-	pub sgx_mrenclave: String,
-
-	#[serde(rename = "sgx-mrsigner")]
-	pub sgx_mrsigner: String,
-
-	pub svn: u32,
-	pub tee: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Decode, Encode)]
-pub struct MAAResponse {
-	pub token: String,
-}
-
-impl Default for MAAPolicy {
-	fn default() -> Self {
-		MAAPolicy {
-			is_debuggable: true,
-			product_id: 0_u32,
-			sgx_mrenclave: Default::default(),
-			sgx_mrsigner: Default::default(),
-			svn: 0u32,
-			tee: "sgx".to_string(),
-		}
-	}
-}
+type EnclaveResult<T> = Result<T, MAAError>;
+pub use azure_attest_core::maa::{MAAPolicy, MAAResponse};
 
 //  Trait to do Microsoft Azure Attestation
 pub trait MAAHandler {
-	//  Verify DCAP quote from MAA
+	//  Verify DCAP quote from MAA, returning the claims of the issued token
 	fn azure_attest(&self, quote: &[u8]) -> EnclaveResult<Vec<u8>>;
 }
 
-pub struct MAAService;
-impl MAAService {
-	pub fn parse_maa_policy(writer: &[u8]) -> EnclaveResult<Vec<u8>> {
-		let res: MAAResponse = serde_json::from_slice(&writer).unwrap();
-		println!("res: {:?}", res);
+/// Attestation of SGX quotes over a raw TLS connection, for use inside an enclave. Everything it
+/// needs is passed in: it reads no files.
+#[derive(Clone, Debug)]
+pub struct MAAService {
+	endpoint: String,
+	token: String,
+	tls_roots: Vec<String>,
+	tls_pinning: TlsPinning,
+}
 
-		let decompose_token: Vec<&str> = res.token.split(".").collect();
-		if decompose_token.len() != 3 {
-			println!("JSON Web Tokens must have 3 components delimited by '.' characters.");
+impl MAAService {
+	pub fn new(endpoint: impl Into<String>, token: impl Into<String>) -> Self {
+		Self {
+			endpoint: endpoint.into(),
+			token: token.into(),
+			tls_roots: Vec::new(),
+			tls_pinning: TlsPinning::default(),
 		}
+	}
 
-		let policy = base64::decode(decompose_token[1]).unwrap();
+	pub fn from_config(config: &Config) -> Self {
+		Self::new(config.endpoint.clone(), config.token.clone())
+	}
 
-		Ok(policy)
+	/// Trust the PEM encoded root certificates `pem` for the provider TLS connection, on top of
+	/// the default roots.
+	#[must_use]
+	pub fn with_tls_root(mut self, pem: impl Into<String>) -> Self {
+		self.tls_roots.push(pem.into());
+		self
 	}
-}
 
-impl MAAHandler for MAAService {
-	fn azure_attest(&self, quote: &[u8]) -> EnclaveResult<Vec<u8>> {
-		println!("    [Enclave] Entering azure_attest.");
+	/// Check the provider TLS certificates against `pinning`. Roots added with `with_tls_root` are
+	/// trusted on top of those of `pinning`.
+	#[must_use]
+	pub fn with_tls_pinning(mut self, pinning: TlsPinning) -> Self {
+		self.tls_pinning = pinning;
+		self
+	}
 
-		let quote = base64(quote.to_vec());
-		let req_body = json!({
-			"quote": quote
-		})
-		.to_string();
+	fn url(&self) -> String {
+		format!("{}/attest/SgxEnclave?api-version=2020-10-01", self.endpoint.trim_end_matches('/'))
+	}
 
-		let url = Config::default().endpoint + "/attest/SgxEnclave?api-version=2020-10-01";
-		let addr = Uri::try_from(&url[..]).unwrap();
-		let sock = TcpStream::connect((addr.host().unwrap(), addr.corr_port())).unwrap();
-		let mut writer = Vec::new();
+	/// Attest `quote` over `stream`, an established TCP connection to the provider, e.g. one set
+	/// up by the untrusted host.
+	pub fn attest_over<S: Read + Write>(&self, stream: S, quote: &[u8]) -> EnclaveResult<Vec<u8>> {
+		let url = self.url();
+		let addr = Uri::try_from(&url[..]).map_err(|e| MAAError::InvalidEndpoint(e.to_string()))?;
+		let host = addr.host().ok_or(MAAError::InvalidEndpoint(url.clone()))?;
 
-		let mut stream = tls::Config::default().connect(addr.host().unwrap_or(""), sock).unwrap();
+		if !self.tls_pinning.is_empty() {
+			let stream = self.pinned_connect(host, stream)?;
+			return self.attest_tls(&addr, stream, quote)
+		}
+		let mut config = tls::Config::default();
+		for root in &self.tls_roots {
+			config
+				.add_root_cert_content_pem_file(root)
+				.map_err(|e| MAAError::Tls(e.to_string()))?;
+		}
+		let stream = config.connect(host, stream).map_err(|e| MAAError::Tls(e.to_string()))?;
+		self.attest_tls(&addr, stream, quote)
+	}
 
-		let response = RequestBuilder::new(&addr)
+	// Complete the TLS handshake up front, so that a pin mismatch surfaces as a TLS error.
+	fn pinned_connect<S: Read + Write>(
+		&self,
+		host: &str,
+		mut stream: S,
+	) -> EnclaveResult<rustls::StreamOwned<rustls::ClientSession, S>> {
+		let config =
+			self.tls_pinning.legacy_rustls_config(&self.tls_roots).map_err(MAAError::Tls)?;
+		let dns_name = webpki::DNSNameRef::try_from_ascii_str(host)
+			.map_err(|_| MAAError::InvalidEndpoint(host.to_string()))?;
+		let mut session = rustls::ClientSession::new(&Arc::new(config), dns_name);
+		while rustls::Session::is_handshaking(&session) {
+			rustls::Session::complete_io(&mut session, &mut stream)
+				.map_err(|e| MAAError::Tls(e.to_string()))?;
+		}
+		Ok(rustls::StreamOwned::new(session, stream))
+	}
+
+	fn attest_tls<S: Read + Write>(
+		&self,
+		addr: &Uri,
+		mut stream: S,
+		quote: &[u8],
+	) -> EnclaveResult<Vec<u8>> {
+		let req_body = json!({ "quote": base64(quote.to_vec()) }).to_string();
+		let mut writer = Vec::new();
+		let response = RequestBuilder::new(addr)
 			.method(Method::POST)
 			.body(req_body.as_bytes())
 			.header("Content-Length", &req_body.len())
 			.header("Connection", "Close")
 			.header("Content-Type", "application/json")
-			.header("Authorization", &format!("Bearer {}", Config::default().token))
+			.header("Authorization", &format!("Bearer {}", self.token))
 			.send(&mut stream, &mut writer)
-			.unwrap();
-		let status_code = response.status_code();
-		let reason = response.reason();
+			.map_err(|e| MAAError::Http(e.to_string()))?;
 
-		println!(">>> response status code: {}", status_code);
-		println!(">>> response reason: {}", reason);
+		let status = response.status_code();
+		if !status.is_success() {
+			return Err(MAAError::Status {
+				code: status.into(),
+				reason: response.reason().to_string(),
+				body: String::from_utf8_lossy(&writer).to_string(),
+			})
+		}
 
-		let resp_string = String::from_utf8_lossy(&writer);
-		println!("resp_string: {}", resp_string);
+		let chunked = response
+			.headers()
+			.get("Transfer-Encoding")
+			.is_some_and(|encoding| encoding.to_ascii_lowercase().contains("chunked"));
+		let body = if chunked { dechunk(&writer)? } else { writer };
 
-		Self::parse_maa_policy(&writer)
+		Self::parse_maa_policy(&body)
+	}
+
+	/// The claims of the token in the attestation response `writer`.
+	pub fn parse_maa_policy(writer: &[u8]) -> EnclaveResult<Vec<u8>> {
+		let res: MAAResponse =
+			serde_json::from_slice(writer).map_err(|e| MAAError::Response(e.to_string()))?;
+
+		let decompose_token: Vec<&str> = res.token.split('.').collect();
+		if decompose_token.len() != 3 {
+			return Err(MAAError::Response(
+				"JSON Web Tokens must have 3 components delimited by '.' characters.".into(),
+			))
+		}
+
+		base64url_decode(decompose_token[1]).map_err(MAAError::Response)
 	}
 }
 
+impl MAAHandler for MAAService {
+	fn azure_attest(&self, quote: &[u8]) -> EnclaveResult<Vec<u8>> {
+		let url = self.url();
+		let addr = Uri::try_from(&url[..]).map_err(|e| MAAError::InvalidEndpoint(e.to_string()))?;
+		let host = addr.host().ok_or(MAAError::InvalidEndpoint(url.clone()))?;
+		let sock = TcpStream::connect((host, addr.corr_port()))
+			.map_err(|e| MAAError::Connect(e.to_string()))?;
+
+		self.attest_over(sock, quote)
+	}
+}
+
+/// Decode a body sent with `Transfer-Encoding: chunked`. Chunk extensions and trailers are
+/// ignored.
+pub fn dechunk(mut body: &[u8]) -> EnclaveResult<Vec<u8>> {
+	let mut decoded = Vec::new();
+	loop {
+		let line_end = find_crlf(body).ok_or(MAAError::Chunked("missing chunk size".into()))?;
+		let line = std::str::from_utf8(&body[..line_end])
+			.map_err(|_| MAAError::Chunked("chunk size is not text".into()))?;
+		let size = line.split(';').next().unwrap_or_default().trim();
+		let size = usize::from_str_radix(size, 16)
+			.map_err(|_| MAAError::Chunked(format!("invalid chunk size {:?}", size)))?;
+		body = &body[line_end + 2..];
+
+		if size == 0 {
+			return Ok(decoded)
+		}
+		// The size comes from the host: it may exceed anything addressable.
+		let end = size
+			.checked_add(2)
+			.ok_or_else(|| MAAError::Chunked(format!("chunk size {:#x} overflows", size)))?;
+		if body.get(size..end) != Some(&b"\r\n"[..]) {
+			return Err(MAAError::Chunked("truncated chunk".into()))
+		}
+		decoded.extend_from_slice(&body[..size]);
+		body = &body[end..];
+	}
+}
+
+fn find_crlf(bytes: &[u8]) -> Option<usize> {
+	bytes.windows(2).position(|window| window == b"\r\n")
+}
+
 #[cfg(test)]
 pub mod tests {
 	use azure_core::base64;
 
 	use super::*;
 
+	// Attests against a live provider, e.g.
+	// `MAA_ENDPOINT=https://... MAA_TOKEN=... cargo test -- --ignored azure_attest_works`.
 	#[test]
+	#[ignore = "needs a live provider, MAA_ENDPOINT and MAA_TOKEN"]
 	pub fn azure_attest_works() {
 		pub const DCAP_QUOTE: &[u8] = include_bytes!("./quote_sample");
 		let quote = hex::decode(DCAP_QUOTE).unwrap();
 
-		let s = MAAService;
+		let endpoint = std::env::var("MAA_ENDPOINT").expect("MAA_ENDPOINT");
+		let s = MAAService::new(endpoint, std::env::var("MAA_TOKEN").expect("MAA_TOKEN"));
 		let ret = s.azure_attest(&quote);
-		println!("ret: {:?}", ret);
+		assert!(ret.is_ok(), "{:?}", ret);
 	}
 
 	#[test]
@@ -142,4 +237,24 @@ pub mod tests {
 		let v: MAAPolicy = serde_json::from_slice(&x).unwrap();
 		println!("Policy: {:?}", v);
 	}
+
+	#[test]
+	fn chunked_response_decodes() {
+		let body =
+			b"7\r\n{\"token\r\n8;ext=1\r\n\":\"a.e30\r\n3\r\n.\"}\r\n0\r\nTrailer: x\r\n\r\n";
+		let body = dechunk(body).unwrap();
+		assert_eq!(body, br#"{"token":"a.e30."}"#);
+		assert_eq!(MAAService::parse_maa_policy(&body), Ok(b"{}".to_vec()));
+
+		assert!(matches!(dechunk(b"5\r\nab\r\n0\r\n\r\n"), Err(MAAError::Chunked(_))));
+		assert!(matches!(dechunk(b"zz\r\n"), Err(MAAError::Chunked(_))));
+		let huge = format!("{:x}\r\nab\r\n0\r\n\r\n", usize::MAX);
+		assert!(matches!(dechunk(huge.as_bytes()), Err(MAAError::Chunked(_))));
+		let huge = format!("{:x}\r\nab\r\n0\r\n\r\n", usize::MAX - 1);
+		assert!(matches!(dechunk(huge.as_bytes()), Err(MAAError::Chunked(_))));
+		assert!(matches!(
+			MAAService::parse_maa_policy(br#"{"token":"a.b"}"#),
+			Err(MAAError::Response(_))
+		));
+	}
 }
@@ -1,6 +1,18 @@
+pub mod batch;
 pub mod client;
+pub mod endpoints;
+pub mod error;
 pub mod maa;
+pub mod nonce;
+pub mod pinning;
 pub mod pipeline;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod signing_keys;
+pub mod token;
+
+#[cfg(test)]
+pub(crate) mod stand_in;
 
 use bytes::Bytes;
 use serde::{de::DeserializeOwned, Serialize};
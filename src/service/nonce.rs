@@ -0,0 +1,137 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::utils::{base64url, AttestationResult};
+
+/// Length of a generated nonce, before encoding.
+pub const NONCE_LEN: usize = 32;
+
+/// How long an issued nonce can be redeemed.
+pub const DEFAULT_NONCE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A fresh random nonce, base64url encoded.
+pub fn generate() -> Result<String, String> {
+	let mut nonce = [0u8; NONCE_LEN];
+	SystemRandom::new()
+		.fill(&mut nonce)
+		.map_err(|_| "Failed to generate a nonce".to_string())?;
+	Ok(base64url(&nonce))
+}
+
+/// Check that the token carries the `expected` nonce.
+pub fn check(result: &AttestationResult, expected: &str) -> Result<(), String> {
+	match result.nonce.as_deref() {
+		Some(nonce) if nonce == expected => Ok(()),
+		Some(nonce) => Err(format!("Token nonce {} does not match {}", nonce, expected)),
+		None => Err("Token has no nonce".into()),
+	}
+}
+
+/// Nonces handed out to attesters, for challenge/response flows where the relying party issues
+/// the nonce and later receives the token.
+pub trait NonceStore: Send + Sync {
+	/// Remember `nonce` as issued.
+	fn insert(&self, nonce: &str);
+
+	/// Forget `nonce`, returning whether it was issued and has not expired. A nonce can only be
+	/// redeemed once.
+	fn redeem(&self, nonce: &str) -> bool;
+
+	/// Generate a nonce and remember it.
+	fn issue(&self) -> Result<String, String> {
+		let nonce = generate()?;
+		self.insert(&nonce);
+		Ok(nonce)
+	}
+
+	/// Check that the token carries a nonce issued by this store, and redeem it.
+	fn verify(&self, result: &AttestationResult) -> Result<(), String> {
+		let nonce = result.nonce.as_deref().ok_or("Token has no nonce")?;
+		if !self.redeem(nonce) {
+			return Err(format!("Nonce {} was not issued, expired or was already used", nonce))
+		}
+		Ok(())
+	}
+}
+
+/// A `NonceStore` in memory, whose nonces expire after a TTL. Clones share the same nonces.
+#[derive(Clone)]
+pub struct InMemoryNonceStore {
+	nonces: Arc<Mutex<HashMap<String, Instant>>>,
+	ttl: Duration,
+}
+
+impl Default for InMemoryNonceStore {
+	fn default() -> Self {
+		Self::new(DEFAULT_NONCE_TTL)
+	}
+}
+
+impl InMemoryNonceStore {
+	pub fn new(ttl: Duration) -> Self {
+		Self { nonces: Arc::new(Mutex::new(HashMap::new())), ttl }
+	}
+
+	/// Number of nonces issued and not yet redeemed or expired.
+	pub fn len(&self) -> usize {
+		let now = Instant::now();
+		self.nonces.lock().unwrap().values().filter(|expires| **expires > now).count()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+impl NonceStore for InMemoryNonceStore {
+	fn insert(&self, nonce: &str) {
+		let now = Instant::now();
+		let mut nonces = self.nonces.lock().unwrap();
+		nonces.retain(|_, expires| *expires > now);
+		nonces.insert(nonce.to_string(), now + self.ttl);
+	}
+
+	fn redeem(&self, nonce: &str) -> bool {
+		match self.nonces.lock().unwrap().remove(nonce) {
+			Some(expires) => expires > Instant::now(),
+			None => false,
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	fn result(nonce: &str) -> AttestationResult {
+		AttestationResult { nonce: Some(nonce.into()), ..Default::default() }
+	}
+
+	#[test]
+	fn nonce_is_redeemed_once() {
+		let store = InMemoryNonceStore::default();
+		let nonce = store.issue().unwrap();
+		assert_eq!(nonce.len(), 43);
+		assert_ne!(nonce, generate().unwrap());
+
+		assert!(store.verify(&result("forged")).is_err());
+		assert!(store.verify(&result(&nonce)).is_ok());
+		assert!(store.verify(&result(&nonce)).is_err());
+		assert!(store.is_empty());
+	}
+
+	#[test]
+	fn nonce_expires() {
+		let store = InMemoryNonceStore::new(Duration::ZERO);
+		let nonce = store.issue().unwrap();
+		assert!(!store.redeem(&nonce));
+
+		assert!(check(&result(&nonce), &nonce).is_ok());
+		assert!(check(&AttestationResult::default(), &nonce).is_err());
+	}
+}
@@ -0,0 +1,314 @@
+//! Pinning of the TLS certificates of attestation providers, by the SHA-256 of their public keys or
+//! by a custom set of root CAs.
+//!
+//! Inside an enclave the untrusted host controls the network, and may well control a CA of the
+//! default root store too. Pins are checked on top of the usual chain validation, so a pinned
+//! connection still needs a chain to a trusted root: the pin then narrows which chains are good.
+
+use std::{sync::Arc, time::SystemTime};
+
+use azure_core::base64;
+use ring::digest::{digest, SHA256};
+
+use crate::x509;
+
+/// The certificates a provider TLS connection may present. The default pins nothing and trusts
+/// the default root store.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsPinning {
+	spki_sha256: Vec<[u8; 32]>,
+	roots: Vec<Vec<u8>>,
+}
+
+impl TlsPinning {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Require a certificate of the presented chain, leaf or intermediate, to have the public key
+	/// info hashing to `hash`. Several pins accept any of them, e.g. the current and next key.
+	#[must_use]
+	pub fn pin_spki_sha256(mut self, hash: [u8; 32]) -> Self {
+		self.spki_sha256.push(hash);
+		self
+	}
+
+	/// `pin_spki_sha256` with the hash base64 encoded, as in `pin-sha256` of HTTP public key
+	/// pinning and the output of `openssl ... | openssl dgst -sha256 -binary | base64`.
+	pub fn pin_spki_sha256_base64(self, pin: &str) -> Result<Self, String> {
+		let hash = base64::decode(pin.trim()).map_err(|e| format!("Invalid pin {}: {}", pin, e))?;
+		let hash = hash.try_into().map_err(|_| format!("Invalid pin {}: not 32 bytes", pin))?;
+		Ok(self.pin_spki_sha256(hash))
+	}
+
+	/// Trust the DER encoded root certificate `der` instead of the default root store.
+	#[must_use]
+	pub fn root_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+		self.roots.push(der.into());
+		self
+	}
+
+	/// Trust the PEM encoded root certificates of `pem` instead of the default root store.
+	pub fn root_pem(mut self, pem: &str) -> Result<Self, String> {
+		let roots = pem_certificates(pem)?;
+		if roots.is_empty() {
+			return Err("No certificate found in the PEM root".into())
+		}
+		self.roots.extend(roots);
+		Ok(self)
+	}
+
+	/// Whether nothing is pinned, i.e. connections are checked as without pinning.
+	pub fn is_empty(&self) -> bool {
+		self.spki_sha256.is_empty() && self.roots.is_empty()
+	}
+
+	/// Check the DER encoded certificates `chain`, already validated up to a trusted root,
+	/// against the pins.
+	pub fn check<'a>(&self, chain: impl IntoIterator<Item = &'a [u8]>) -> Result<(), String> {
+		if self.spki_sha256.is_empty() {
+			return Ok(())
+		}
+		let mut presented = Vec::new();
+		for cert in chain {
+			let hash = spki_sha256(cert)?;
+			if self.spki_sha256.contains(&hash) {
+				return Ok(())
+			}
+			presented.push(base64::encode(hash));
+		}
+		Err(format!(
+			"TLS certificate pin mismatch: the provider presented keys {}, none of which is pinned",
+			presented.join(", ")
+		))
+	}
+
+	/// A `reqwest` TLS configuration enforcing the pins.
+	pub(crate) fn rustls_config(&self) -> Result<rustls21::ClientConfig, String> {
+		let mut roots = rustls21::RootCertStore::empty();
+		if self.roots.is_empty() {
+			roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|anchor| {
+				rustls21::OwnedTrustAnchor::from_subject_spki_name_constraints(
+					anchor.subject,
+					anchor.spki,
+					anchor.name_constraints,
+				)
+			}));
+		}
+		for root in &self.roots {
+			roots
+				.add(&rustls21::Certificate(root.clone()))
+				.map_err(|e| format!("Invalid TLS root: {}", e))?;
+		}
+
+		let verifier = PinningVerifier {
+			inner: rustls21::client::WebPkiVerifier::new(roots, None),
+			pinning: self.clone(),
+		};
+		Ok(rustls21::ClientConfig::builder()
+			.with_safe_defaults()
+			.with_custom_certificate_verifier(Arc::new(verifier))
+			.with_no_client_auth())
+	}
+
+	/// A TLS configuration enforcing the pins for the `rustls` of `MAAService`, trusting the PEM
+	/// encoded `extra_roots` too.
+	pub(crate) fn legacy_rustls_config(
+		&self,
+		extra_roots: &[String],
+	) -> Result<rustls::ClientConfig, String> {
+		let mut config = rustls::ClientConfig::new();
+		if self.roots.is_empty() {
+			config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+		}
+		for root in &self.roots {
+			config
+				.root_store
+				.add(&rustls::Certificate(root.clone()))
+				.map_err(|e| format!("Invalid TLS root: {:?}", e))?;
+		}
+		for pem in extra_roots {
+			for root in pem_certificates(pem)? {
+				config
+					.root_store
+					.add(&rustls::Certificate(root))
+					.map_err(|e| format!("Invalid TLS root: {:?}", e))?;
+			}
+		}
+
+		config.dangerous().set_certificate_verifier(Arc::new(LegacyPinningVerifier {
+			inner: rustls::WebPKIVerifier::new(),
+			pinning: self.clone(),
+		}));
+		Ok(config)
+	}
+}
+
+struct PinningVerifier {
+	inner: rustls21::client::WebPkiVerifier,
+	pinning: TlsPinning,
+}
+
+impl rustls21::client::ServerCertVerifier for PinningVerifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &rustls21::Certificate,
+		intermediates: &[rustls21::Certificate],
+		server_name: &rustls21::ServerName,
+		scts: &mut dyn Iterator<Item = &[u8]>,
+		ocsp_response: &[u8],
+		now: SystemTime,
+	) -> Result<rustls21::client::ServerCertVerified, rustls21::Error> {
+		let verified = self.inner.verify_server_cert(
+			end_entity,
+			intermediates,
+			server_name,
+			scts,
+			ocsp_response,
+			now,
+		)?;
+		let chain = std::iter::once(end_entity).chain(intermediates).map(|cert| &cert.0[..]);
+		self.pinning.check(chain).map_err(rustls21::Error::General)?;
+		Ok(verified)
+	}
+}
+
+struct LegacyPinningVerifier {
+	inner: rustls::WebPKIVerifier,
+	pinning: TlsPinning,
+}
+
+impl rustls::ServerCertVerifier for LegacyPinningVerifier {
+	fn verify_server_cert(
+		&self,
+		roots: &rustls::RootCertStore,
+		presented_certs: &[rustls::Certificate],
+		dns_name: webpki::DNSNameRef,
+		ocsp_response: &[u8],
+	) -> Result<rustls::ServerCertVerified, rustls::TLSError> {
+		let verified =
+			self.inner.verify_server_cert(roots, presented_certs, dns_name, ocsp_response)?;
+		let chain = presented_certs.iter().map(|cert| &cert.0[..]);
+		self.pinning.check(chain).map_err(rustls::TLSError::General)?;
+		Ok(verified)
+	}
+}
+
+/// SHA-256 of the DER encoded SubjectPublicKeyInfo of the DER encoded X.509 certificate `cert`.
+pub fn spki_sha256(cert: &[u8]) -> Result<[u8; 32], String> {
+	let mut hash = [0u8; 32];
+	hash.copy_from_slice(digest(&SHA256, x509::spki(cert)?).as_ref());
+	Ok(hash)
+}
+
+// The DER certificates of the `CERTIFICATE` blocks of `pem`.
+fn pem_certificates(pem: &str) -> Result<Vec<Vec<u8>>, String> {
+	let mut certificates = Vec::new();
+	let mut block: Option<String> = None;
+	for line in pem.lines().map(str::trim) {
+		match (&mut block, line) {
+			(None, "-----BEGIN CERTIFICATE-----") => block = Some(String::new()),
+			(Some(base64), "-----END CERTIFICATE-----") => {
+				certificates.push(
+					base64::decode(&*base64)
+						.map_err(|e| format!("Invalid PEM certificate: {}", e))?,
+				);
+				block = None;
+			},
+			(Some(base64), line) => base64.push_str(line),
+			(None, _) => {},
+		}
+	}
+	if block.is_some() {
+		return Err("Unterminated PEM certificate".into())
+	}
+	Ok(certificates)
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::{
+		io::{BufRead, BufReader, Write},
+		net::{TcpListener, TcpStream},
+		thread,
+	};
+
+	use super::*;
+	use crate::service::{error::MAAError, maa::MAAService, pipeline::Pipeline};
+
+	// A provider stand-in serving HTTPS with a self-signed certificate for `localhost`, answering
+	// every request with `body`.
+	fn serve_tls(body: &'static str) -> (u16, rcgen::Certificate) {
+		let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+		let config = rustls21::ServerConfig::builder()
+			.with_safe_defaults()
+			.with_no_client_auth()
+			.with_single_cert(
+				vec![rustls21::Certificate(cert.serialize_der().unwrap())],
+				rustls21::PrivateKey(cert.serialize_private_key_der()),
+			)
+			.unwrap();
+		let config = Arc::new(config);
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(mut stream) = stream else { continue };
+				let mut conn = rustls21::ServerConnection::new(config.clone()).unwrap();
+				let mut tls = rustls21::Stream::new(&mut conn, &mut stream);
+				let mut reader = BufReader::new(&mut tls);
+				let mut line = String::new();
+				// Pinning failures abort the handshake: on to the next connection.
+				while reader.read_line(&mut line).is_ok_and(|read| read > 0) {
+					if line.trim().is_empty() {
+						break
+					}
+					line.clear();
+				}
+				let _ = write!(
+					tls,
+					"HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+					body.len(),
+					body
+				);
+				let _ = tls.flush();
+			}
+		});
+		(port, cert)
+	}
+
+	#[test]
+	fn pins_are_enforced_on_both_transports() {
+		let (port, cert) = serve_tls("{}");
+		let der = cert.serialize_der().unwrap();
+		let pin = spki_sha256(&der).unwrap();
+		let url = format!("https://localhost:{}/", port);
+		let get = |pinning: &TlsPinning| {
+			let pipeline = Pipeline::with_tls_pinning(pinning)?;
+			pipeline.send(pipeline.client.get(&url).build().map_err(|e| e.to_string())?)
+		};
+
+		let trusted = TlsPinning::new().root_pem(&cert.serialize_pem().unwrap()).unwrap();
+		assert_eq!(trusted.clone().root_der(der.clone()).roots.len(), 2);
+		assert!(get(&trusted.clone().pin_spki_sha256(pin)).unwrap().status().is_success());
+
+		let other = TlsPinning::new().pin_spki_sha256_base64(&base64::encode([7u8; 32])).unwrap();
+		let err = get(&trusted.clone().pin_spki_sha256(other.spki_sha256[0])).unwrap_err();
+		assert!(err.contains("pin mismatch"), "{}", err);
+		assert!(err.contains(&base64::encode(pin)), "{}", err);
+		// Pinning the key does not spare the chain validation.
+		assert!(get(&TlsPinning::new().pin_spki_sha256(pin)).is_err());
+
+		let service = MAAService::new(format!("https://localhost:{}", port), "token")
+			.with_tls_pinning(trusted.pin_spki_sha256([7; 32]));
+		let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+		match service.attest_over(stream, b"quote") {
+			Err(MAAError::Tls(err)) => assert!(err.contains("pin mismatch"), "{}", err),
+			other => panic!("expected a TLS error, got {:?}", other),
+		}
+
+		assert!(TlsPinning::new().pin_spki_sha256_base64("AQID").is_err());
+		assert!(spki_sha256(&der[..der.len() / 2]).is_err());
+	}
+}
@@ -1,5 +1,7 @@
 use reqwest::blocking::{Client, Request, Response};
 
+use super::pinning::TlsPinning;
+
 #[derive(Clone)]
 pub struct Pipeline {
 	pub client: Client,
@@ -10,13 +12,24 @@ impl Pipeline {
 		Self { client: Client::new() }
 	}
 
+	/// A pipeline whose TLS connections are checked against `pinning`.
+	pub fn with_tls_pinning(pinning: &TlsPinning) -> Result<Pipeline, String> {
+		if pinning.is_empty() {
+			return Ok(Self::new())
+		}
+		let client = Client::builder()
+			.use_preconfigured_tls(pinning.rustls_config()?)
+			.build()
+			.map_err(|e| e.to_string())?;
+		Ok(Self { client })
+	}
+
 	pub fn send(&self, request: Request) -> Result<Response, String> {
 		// println!("URL: {:?}", request.url().as_str());
 		// println!("Headers: {:?}", request.headers());
 		// println!("Method: {:?}", request.method());
 		// println!("Body: {:?}", request.body());
 
-		let response = self.client.execute(request).unwrap();
-		Ok(response)
+		self.client.execute(request).map_err(|e| e.to_string())
 	}
 }
@@ -0,0 +1,293 @@
+//! A relying-party HTTP service, checking tokens and attesting evidence for services that cannot
+//! use this crate themselves:
+//!
+//! - `POST /verify`, `{"token": ..., "expected": ...}`: verify a token, and check the enclave
+//!   against the optional expectations, in the `EnclaveInfo` JSON format.
+//! - `POST /attest`, `{"tee": ..., "evidence": ..., "runtime_data": ..., "runtime_data_type": ...,
+//!   "expected": ...}`: attest evidence and verify the issued token. `tee` and `runtime_data_type`
+//!   are as in batch manifests, the evidence and runtime data are encoded as in evidence files.
+//! - `GET /healthz`: whether the service runs. `GET /readyz`: whether it can verify tokens.
+//!
+//! Both answer with the verdict, `passed`, `mismatch` or `rejected`, the checks made and the
+//! claims of the verified token, or the error the token or evidence was rejected for.
+
+use std::{convert::Infallible, net::TcpListener};
+
+use hyper::{
+	body::HttpBody,
+	header::CONTENT_TYPE,
+	service::{make_service_fn, service_fn},
+	Body, Request, Response, Server,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+	batch::{BatchAttestor, BatchItem, ItemResult, Outcome},
+	client::Client,
+};
+use crate::enclaves::{
+	enclave_info::{Check, EnclaveInfo},
+	evidence::{self, Evidence},
+	model::{AttestationType, DataType, RuntimeData},
+};
+
+/// Largest request body accepted.
+pub const MAX_BODY_SIZE: usize = 1 << 20;
+
+#[derive(Deserialize)]
+struct VerifyRequest {
+	token: String,
+	#[serde(default)]
+	expected: Option<EnclaveInfo>,
+}
+
+#[derive(Deserialize)]
+struct AttestRequest {
+	tee: String,
+	evidence: String,
+	#[serde(default)]
+	runtime_data: Option<String>,
+	#[serde(default)]
+	runtime_data_type: Option<String>,
+	#[serde(default)]
+	expected: Option<EnclaveInfo>,
+}
+
+impl AttestRequest {
+	fn into_item(self) -> Result<BatchItem, String> {
+		let tee = match self.tee.as_str() {
+			"sgx" => AttestationType::SgxEnclave,
+			"openenclave" => AttestationType::OpenEnclave,
+			"sev-snp" => AttestationType::SevSnpVm,
+			tee => return Err(format!("Unsupported tee {}", tee)),
+		};
+		if self.expected.is_some() && tee == AttestationType::SevSnpVm {
+			return Err("expected applies to SGX and OpenEnclave evidence only".into())
+		}
+
+		let mut evidence = Evidence::from_bytes(self.evidence.as_bytes())?;
+		if let Some(data) = &self.runtime_data {
+			let data_type = match self.runtime_data_type.as_deref() {
+				None | Some("binary") => DataType::Binary,
+				Some("json") => DataType::Json,
+				Some(kind) => return Err(format!("Unsupported runtime_data_type {}", kind)),
+			};
+			let (_, data) = evidence::decode(data.as_bytes());
+			evidence.runtime_data =
+				Some(RuntimeData::new(azure_core::base64::encode(data), data_type));
+		}
+
+		Ok(BatchItem { name: "attest".into(), tee, evidence, expected: self.expected })
+	}
+}
+
+/// The handlers of the service, on the attestation client of the configured providers.
+#[derive(Clone)]
+pub struct VerificationService {
+	client: Client,
+}
+
+impl VerificationService {
+	pub fn new(client: Client) -> Self {
+		Self { client }
+	}
+
+	/// Answer a request with a status code and JSON body. Blocks on the providers.
+	pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+		match (method, path) {
+			("GET", "/healthz") => (200, json!({ "status": "ok" })),
+			("GET", "/readyz") => match self.client.ready() {
+				Ok(()) => (200, json!({ "status": "ready" })),
+				Err(err) => (503, json!({ "status": "unavailable", "error": err })),
+			},
+			("POST", "/verify") => match serde_json::from_slice::<VerifyRequest>(body) {
+				Ok(request) => {
+					let verified = self.client.verify_token(&request.token);
+					let (outcome, checks) = Outcome::of(verified, request.expected.as_ref());
+					(200, verdict(&outcome, &checks))
+				},
+				Err(err) => (400, json!({ "error": format!("Invalid verify request: {}", err) })),
+			},
+			("POST", "/attest") => match serde_json::from_slice::<AttestRequest>(body)
+				.map_err(|e| format!("Invalid attest request: {}", e))
+				.and_then(AttestRequest::into_item)
+			{
+				Ok(item) => {
+					let ItemResult { outcome, checks, .. } =
+						BatchAttestor::new(self.client.attestation_client()).attest(&item);
+					(200, verdict(&outcome, &checks))
+				},
+				Err(err) => (400, json!({ "error": err })),
+			},
+			(_, "/healthz" | "/readyz" | "/verify" | "/attest") =>
+				(405, json!({ "error": format!("{} is not allowed on {}", method, path) })),
+			_ => (404, json!({ "error": format!("No such endpoint {}", path) })),
+		}
+	}
+}
+
+fn verdict(outcome: &Outcome, checks: &[Check]) -> Value {
+	match outcome {
+		Outcome::Passed(result) =>
+			json!({ "verdict": "passed", "checks": checks, "claims": result.claims }),
+		Outcome::Mismatch(result) =>
+			json!({ "verdict": "mismatch", "checks": checks, "claims": result.claims }),
+		Outcome::Error(err) => json!({ "verdict": "rejected", "error": err }),
+	}
+}
+
+/// Serve `service` on `listener` until the process ends.
+pub async fn serve(listener: TcpListener, service: VerificationService) -> Result<(), String> {
+	listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+	let make_service = make_service_fn(move |_| {
+		let service = service.clone();
+		async move { Ok::<_, Infallible>(service_fn(move |request| respond(service.clone(), request))) }
+	});
+	Server::from_tcp(listener)
+		.map_err(|e| e.to_string())?
+		.serve(make_service)
+		.await
+		.map_err(|e| e.to_string())
+}
+
+async fn respond(
+	service: VerificationService,
+	request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+	let method = request.method().to_string();
+	let path = request.uri().path().to_string();
+	let (status, body) = match read_body(request.into_body()).await {
+		// The attestation client blocks.
+		Ok(body) => tokio::task::spawn_blocking(move || service.handle(&method, &path, &body))
+			.await
+			.unwrap_or_else(|e| (500, json!({ "error": e.to_string() }))),
+		Err(err) => (400, json!({ "error": err })),
+	};
+
+	let response = Response::builder()
+		.status(status)
+		.header(CONTENT_TYPE, "application/json")
+		.body(Body::from(body.to_string()))
+		.unwrap_or_default();
+	Ok(response)
+}
+
+async fn read_body(mut body: Body) -> Result<Vec<u8>, String> {
+	let mut bytes = Vec::new();
+	while let Some(chunk) = body.data().await {
+		bytes.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+		if bytes.len() > MAX_BODY_SIZE {
+			return Err(format!("Request body is larger than {} bytes", MAX_BODY_SIZE))
+		}
+	}
+	Ok(bytes)
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::thread;
+
+	use super::*;
+	use crate::{
+		service::{
+			client::ClientBuilder,
+			stand_in::{SigningStandIn, StandIn},
+		},
+		utils::base64url,
+	};
+
+	const SGX_QUOTE: &str = include_str!("../../quotes/sgx_enclave_quote.txt");
+	const SGX_EHD: &str = include_str!("../../quotes/sgx_enclave_ehd.txt");
+	const ENCLAVE_INFO: &str = include_str!("../../quotes/enclave.info.securityversion.json");
+
+	// Serve the verification service for the providers at `endpoint` on a local port.
+	fn start(endpoint: url::Url) -> String {
+		let client = ClientBuilder::new("token".into(), endpoint).build().unwrap();
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		thread::spawn(move || {
+			let runtime = tokio::runtime::Runtime::new().unwrap();
+			runtime.block_on(serve(listener, VerificationService::new(client))).unwrap();
+		});
+		url
+	}
+
+	fn post(url: &str, path: &str, body: Value) -> (u16, Value) {
+		let response = reqwest::blocking::Client::new()
+			.post(format!("{}{}", url, path))
+			.json(&body)
+			.send();
+		let response = response.unwrap();
+		(response.status().as_u16(), response.json().unwrap())
+	}
+
+	#[test]
+	fn tokens_and_evidence_are_verified() {
+		let provider = SigningStandIn::serve();
+		let claims = json!({
+			"x-ms-sgx-ehd": base64url(&hex::decode(SGX_EHD.trim()).unwrap()),
+			"sgx-mrenclave": "d37d983a85d63fb49649610e2eba0930ecdbff6d113aca3ff3fc7261696c0134",
+			"sgx-mrsigner": "feb995eb86c349ac98e5afbbb5732ca7376ec9979002702ea17ad476e0853a04",
+			"svn": 8888,
+			"is-debuggable": false,
+		});
+		let token = provider.token(claims.clone());
+		provider.attest_with(claims.clone());
+		let url = start(provider.url());
+		let expected: Value = serde_json::from_str(ENCLAVE_INFO).unwrap();
+
+		let response = reqwest::blocking::get(format!("{}/readyz", url)).unwrap();
+		assert_eq!(response.status().as_u16(), 200);
+
+		let (status, verdict) =
+			post(&url, "/verify", json!({ "token": token, "expected": expected }));
+		assert_eq!((status, &verdict["verdict"]), (200, &json!("passed")));
+		assert_eq!(verdict["claims"]["svn"], 8888);
+		assert_eq!(verdict["checks"].as_array().unwrap().len(), 5);
+
+		let outdated =
+			provider.token(json!({ "svn": 1, "sgx-mrenclave": claims["sgx-mrenclave"] }));
+		let (_, verdict) =
+			post(&url, "/verify", json!({ "token": outdated, "expected": expected }));
+		assert_eq!(verdict["verdict"], "mismatch");
+
+		let forged = token.replace(".ey", ".eX");
+		let (_, verdict) = post(&url, "/verify", json!({ "token": forged }));
+		assert_eq!(verdict["verdict"], "rejected");
+		assert!(verdict["error"].is_string());
+
+		let (status, verdict) = post(
+			&url,
+			"/attest",
+			json!({
+				"tee": "sgx",
+				"evidence": SGX_QUOTE,
+				"runtime_data": SGX_EHD,
+				"expected": expected,
+			}),
+		);
+		assert_eq!((status, &verdict["verdict"]), (200, &json!("passed")), "{}", verdict);
+		let attested = provider.stand_in.requests().pop().unwrap();
+		assert_eq!(attested.path, "/attest/SgxEnclave?api-version=2020-10-01");
+		assert!(attested.body.contains(r#""runtimeData""#));
+
+		let (status, _) = post(&url, "/attest", json!({ "tee": "tpm", "evidence": SGX_QUOTE }));
+		assert_eq!(status, 400);
+		let (status, _) = post(&url, "/verify", json!({ "claims": {} }));
+		assert_eq!(status, 400);
+		assert_eq!(post(&url, "/policies", json!({})).0, 404);
+	}
+
+	#[test]
+	fn readiness_needs_signing_keys() {
+		let provider = StandIn::serve(vec![]);
+		let service = VerificationService::new(
+			ClientBuilder::new("token".into(), provider.url()).build().unwrap(),
+		);
+		assert_eq!(service.handle("GET", "/healthz", b"").0, 200);
+		let (status, body) = service.handle("GET", "/readyz", b"");
+		assert_eq!((status, &body["status"]), (503, &json!("unavailable")));
+		assert_eq!(service.handle("GET", "/verify", b"").0, 405);
+	}
+}
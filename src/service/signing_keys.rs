@@ -0,0 +1,264 @@
+use std::{
+	collections::HashMap,
+	fs,
+	path::PathBuf,
+	sync::{Arc, Mutex, RwLock},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use azure_core::base64;
+use reqwest::blocking::Request;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{pinning::TlsPinning, pipeline::Pipeline};
+
+/// How long a fetched key set is trusted before it is fetched again.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Minimum gap between two refreshes of one provider caused by an unknown `kid`.
+pub const DEFAULT_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[doc = "A token signing key, as published by the attestation provider on its `/certs` endpoint."]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigningKey {
+	pub kid: String,
+
+	#[serde(default)]
+	pub kty: String,
+
+	#[doc = "Certificate chain of the key, base64 (not url) encoded DER, leaf first."]
+	#[serde(default)]
+	pub x5c: Vec<String>,
+}
+
+impl SigningKey {
+	/// DER encoding of the leaf certificate carrying the signing key.
+	pub fn certificate(&self) -> Result<Vec<u8>, String> {
+		let cert = self
+			.x5c
+			.first()
+			.ok_or(format!("Signing key {} has no x5c certificate", self.kid))?;
+		base64::decode(cert).map_err(|e| format!("Invalid x5c for signing key {}: {}", self.kid, e))
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonWebKeySet {
+	keys: Vec<SigningKey>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedKeys {
+	// Seconds since the unix epoch, so that entries survive being persisted.
+	fetched_at: u64,
+	keys: Vec<SigningKey>,
+}
+
+impl CachedKeys {
+	fn find(&self, kid: &str) -> Option<&SigningKey> {
+		self.keys.iter().find(|key| key.kid == kid)
+	}
+}
+
+/// Thread-safe cache of provider signing keys, keyed by provider endpoint.
+///
+/// Key sets are refetched once they are older than the TTL, or when a token names a `kid` the
+/// cache does not know about (the provider rotated its keys). The latter is rate limited by the
+/// minimum refresh interval, so that forged `kid`s cannot be used to hammer the provider.
+/// Clones share the same underlying cache.
+#[derive(Clone)]
+pub struct SigningKeyCache {
+	entries: Arc<RwLock<HashMap<String, CachedKeys>>>,
+	refresh_lock: Arc<Mutex<()>>,
+	ttl: Duration,
+	min_refresh_interval: Duration,
+	persist_path: Option<PathBuf>,
+	pipeline: Pipeline,
+}
+
+impl SigningKeyCache {
+	pub fn new() -> Self {
+		Self {
+			entries: Default::default(),
+			refresh_lock: Default::default(),
+			ttl: DEFAULT_TTL,
+			min_refresh_interval: DEFAULT_MIN_REFRESH_INTERVAL,
+			persist_path: None,
+			pipeline: Pipeline::new(),
+		}
+	}
+
+	#[must_use]
+	pub fn with_ttl(mut self, ttl: Duration) -> Self {
+		self.ttl = ttl;
+		self
+	}
+
+	#[must_use]
+	pub fn with_min_refresh_interval(mut self, interval: Duration) -> Self {
+		self.min_refresh_interval = interval;
+		self
+	}
+
+	/// Fetch key sets over TLS connections checked against `pinning`.
+	pub fn with_tls_pinning(mut self, pinning: &TlsPinning) -> Result<Self, String> {
+		self.pipeline = Pipeline::with_tls_pinning(pinning)?;
+		Ok(self)
+	}
+
+	/// Persist fetched key sets to `path`, loading whatever it already holds.
+	///
+	/// A missing or unreadable file simply starts the cache empty.
+	#[must_use]
+	pub fn with_persistence(mut self, path: impl Into<PathBuf>) -> Self {
+		let path = path.into();
+		if let Some(persisted) = fs::read(&path).ok().and_then(|contents| {
+			serde_json::from_slice::<HashMap<String, CachedKeys>>(&contents).ok()
+		}) {
+			self.entries.write().unwrap().extend(persisted);
+		}
+		self.persist_path = Some(path);
+		self
+	}
+
+	/// Look up the key `kid` of the provider at `endpoint`, fetching the provider keys if needed.
+	pub fn get(&self, endpoint: &Url, kid: &str) -> Result<SigningKey, String> {
+		if let Some(key) = self.lookup(endpoint, kid)? {
+			return Ok(key)
+		}
+
+		// Only one thread refreshes, the others reuse its result.
+		let _guard = self.refresh_lock.lock().unwrap();
+		if let Some(key) = self.lookup(endpoint, kid)? {
+			return Ok(key)
+		}
+
+		self.refresh(endpoint)?.into_iter().find(|key| key.kid == kid).ok_or(format!(
+			"Unknown signing key {} for {}",
+			kid,
+			provider_key(endpoint)
+		))
+	}
+
+	/// Fetch the current keys of the provider at `endpoint`, replacing the cached ones.
+	pub fn refresh(&self, endpoint: &Url) -> Result<Vec<SigningKey>, String> {
+		let url = Url::parse(&format!("{}certs", endpoint)).map_err(|e| e.to_string())?;
+		let response = self.pipeline.send(Request::new(reqwest::Method::GET, url))?;
+		if !response.status().is_success() {
+			return Err(format!("Fetching signing keys failed: {}", response.status()))
+		}
+		let jwks: JsonWebKeySet = response.json().map_err(|e| e.to_string())?;
+
+		let entry = CachedKeys { fetched_at: now(), keys: jwks.keys.clone() };
+		self.entries.write().unwrap().insert(provider_key(endpoint), entry);
+		self.persist();
+
+		Ok(jwks.keys)
+	}
+
+	/// Whether keys of the provider at `endpoint` are cached and younger than the TTL.
+	pub fn is_fresh(&self, endpoint: &Url) -> bool {
+		let entries = self.entries.read().unwrap();
+		entries
+			.get(&provider_key(endpoint))
+			.is_some_and(|entry| now().saturating_sub(entry.fetched_at) < self.ttl.as_secs())
+	}
+
+	// `Ok(None)` means the caller should refresh, an error that it must not.
+	fn lookup(&self, endpoint: &Url, kid: &str) -> Result<Option<SigningKey>, String> {
+		let entries = self.entries.read().unwrap();
+		let Some(entry) = entries.get(&provider_key(endpoint)) else { return Ok(None) };
+
+		let age = now().saturating_sub(entry.fetched_at);
+		if age >= self.ttl.as_secs() {
+			return Ok(None)
+		}
+		if let Some(key) = entry.find(kid) {
+			return Ok(Some(key.clone()))
+		}
+		if age < self.min_refresh_interval.as_secs() {
+			return Err(format!(
+				"Unknown signing key {} for {}, keys were refreshed {}s ago",
+				kid,
+				provider_key(endpoint),
+				age
+			))
+		}
+		Ok(None)
+	}
+
+	// Persistence is best effort, a cache that cannot be written still works in memory.
+	fn persist(&self) {
+		if let Some(path) = &self.persist_path {
+			if let Ok(contents) = serde_json::to_vec(&*self.entries.read().unwrap()) {
+				let _ = fs::write(path, contents);
+			}
+		}
+	}
+}
+
+impl Default for SigningKeyCache {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn provider_key(endpoint: &Url) -> String {
+	endpoint.as_str().trim_end_matches('/').to_string()
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::service::stand_in::StandIn;
+
+	const CERTS: &str = r#"{"keys":[{"kid":"key-1","kty":"RSA","x5c":["AAEC"]}]}"#;
+
+	#[test]
+	fn unknown_kid_refresh_is_rate_limited() {
+		let server = StandIn::serve(vec![("GET /certs", 200, CERTS.to_string())]);
+		let cache = SigningKeyCache::new();
+
+		let key = cache.get(&server.url(), "key-1").unwrap();
+		assert_eq!(key.certificate().unwrap(), vec![0, 1, 2]);
+		assert!(cache.get(&server.url(), "key-1").is_ok());
+		assert_eq!(server.hits(), 1);
+
+		assert!(cache.get(&server.url(), "rotated").is_err());
+		assert_eq!(server.hits(), 1);
+
+		let cache = cache.with_min_refresh_interval(Duration::ZERO);
+		assert!(cache.get(&server.url(), "rotated").is_err());
+		assert_eq!(server.hits(), 2);
+
+		let request = &server.requests()[0];
+		assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/certs"));
+		assert!(request.body.is_empty());
+	}
+
+	#[test]
+	fn persisted_keys_are_reused() {
+		let server = StandIn::serve(vec![("GET /certs", 200, CERTS.to_string())]);
+		let path = std::env::temp_dir().join(format!("maa-keys-{}.json", std::process::id()));
+
+		SigningKeyCache::new()
+			.with_persistence(&path)
+			.get(&server.url(), "key-1")
+			.unwrap();
+		SigningKeyCache::new()
+			.with_persistence(&path)
+			.get(&server.url(), "key-1")
+			.unwrap();
+		assert_eq!(server.hits(), 1);
+
+		let _ = fs::remove_file(path);
+	}
+}
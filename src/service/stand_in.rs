@@ -0,0 +1,197 @@
+//! Minimal local HTTP server standing in for an attestation provider in tests.
+
+use std::{
+	io::{BufRead, BufReader, Read, Write},
+	net::TcpListener,
+	sync::{Arc, Mutex},
+	thread,
+};
+
+use ring::{
+	rand::SystemRandom,
+	signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use url::Url;
+
+use crate::utils::{base64url, base64url_decode};
+
+#[derive(Clone, Debug)]
+pub struct Recorded {
+	pub method: String,
+	pub path: String,
+	pub body: String,
+}
+
+type Respond = Arc<dyn Fn(&Recorded) -> (u16, String) + Send + Sync>;
+
+pub struct StandIn {
+	url: Url,
+	routes: Arc<Mutex<Vec<(&'static str, Respond)>>>,
+	requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl StandIn {
+	/// Serve `routes` until the test ends. A route is `"METHOD /path-prefix"`, the status code
+	/// and the response body; the first matching route wins, unmatched requests get a 404.
+	pub fn serve(routes: Vec<(&'static str, u16, String)>) -> StandIn {
+		let routes: Vec<(&'static str, Respond)> = routes
+			.into_iter()
+			.map(|(route, status, body)| {
+				let respond: Respond = Arc::new(move |_: &Recorded| (status, body.clone()));
+				(route, respond)
+			})
+			.collect();
+		let routes = Arc::new(Mutex::new(routes));
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+		let requests: Arc<Mutex<Vec<Recorded>>> = Default::default();
+
+		let served = routes.clone();
+		let recorded = requests.clone();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(mut stream) = stream else { continue };
+				let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+				let mut request_line = String::new();
+				reader.read_line(&mut request_line).unwrap();
+				let mut parts = request_line.split_whitespace();
+				let method = parts.next().unwrap_or_default().to_string();
+				let path = parts.next().unwrap_or_default().to_string();
+
+				let mut content_length = 0;
+				loop {
+					let mut line = String::new();
+					reader.read_line(&mut line).unwrap();
+					if line.trim().is_empty() {
+						break
+					}
+					if let Some((name, value)) = line.split_once(':') {
+						if name.eq_ignore_ascii_case("content-length") {
+							content_length = value.trim().parse().unwrap_or(0);
+						}
+					}
+				}
+				let mut body = vec![0; content_length];
+				reader.read_exact(&mut body).unwrap();
+
+				let request =
+					Recorded { method, path, body: String::from_utf8_lossy(&body).to_string() };
+				let respond = served
+					.lock()
+					.unwrap()
+					.iter()
+					.find(|(route, _)| {
+						let (m, p) = route.split_once(' ').unwrap();
+						m == request.method && request.path.starts_with(p)
+					})
+					.map(|(_, respond)| respond.clone());
+				let (status, response) =
+					respond.map(|respond| respond(&request)).unwrap_or((404, String::new()));
+				recorded.lock().unwrap().push(request);
+
+				let _ = write!(
+					stream,
+					"HTTP/1.1 {} Stand-In\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+					status,
+					response.len(),
+					response
+				);
+			}
+		});
+
+		StandIn { url, routes, requests }
+	}
+
+	/// Answer requests matching `route` with `respond`, after the routes already served.
+	pub fn respond_with(
+		&self,
+		route: &'static str,
+		respond: impl Fn(&Recorded) -> (u16, String) + Send + Sync + 'static,
+	) {
+		self.routes.lock().unwrap().push((route, Arc::new(respond)));
+	}
+
+	pub fn url(&self) -> Url {
+		self.url.clone()
+	}
+
+	pub fn hits(&self) -> usize {
+		self.requests.lock().unwrap().len()
+	}
+
+	pub fn requests(&self) -> Vec<Recorded> {
+		self.requests.lock().unwrap().clone()
+	}
+}
+
+/// A stand-in provider publishing one ES256 token signing key, and issuing tokens signed with it.
+pub struct SigningStandIn {
+	pub stand_in: StandIn,
+	key_pair: Arc<EcdsaKeyPair>,
+}
+
+impl SigningStandIn {
+	pub fn serve() -> SigningStandIn {
+		let rng = SystemRandom::new();
+		let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+		let mut params = rcgen::CertificateParams::new(vec!["provider".to_string()]);
+		params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+		params.key_pair = Some(rcgen::KeyPair::from_der(pkcs8.as_ref()).unwrap());
+		let certificate = rcgen::Certificate::from_params(params).unwrap().serialize_der().unwrap();
+		let certs = serde_json::json!({
+			"keys": [{ "kid": "stand-in", "kty": "EC", "x5c": [azure_core::base64::encode(certificate)] }],
+		});
+
+		let key_pair =
+			EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref()).unwrap();
+		SigningStandIn {
+			stand_in: StandIn::serve(vec![("GET /certs", 200, certs.to_string())]),
+			key_pair: Arc::new(key_pair),
+		}
+	}
+
+	pub fn url(&self) -> Url {
+		self.stand_in.url()
+	}
+
+	/// A token with `claims`, issued by the stand-in and valid for ten minutes.
+	pub fn token(&self, claims: serde_json::Value) -> String {
+		sign(&self.key_pair, &self.url(), claims)
+	}
+
+	/// Answer attest requests with tokens carrying `claims`, and the nonce and runtime data of the
+	/// request.
+	pub fn attest_with(&self, claims: serde_json::Value) {
+		let key_pair = self.key_pair.clone();
+		let url = self.url();
+		self.stand_in.respond_with("POST /attest/", move |request| {
+			let request: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+			let mut claims = claims.clone();
+			claims["nonce"] = request["nonce"].clone();
+			if let Some(data) = request["runtimeData"]["data"].as_str() {
+				claims["x-ms-sgx-ehd"] = base64url(&base64url_decode(data).unwrap()).into();
+			}
+			let token = sign(&key_pair, &url, claims);
+			(200, serde_json::json!({ "token": token }).to_string())
+		});
+	}
+}
+
+fn sign(key_pair: &EcdsaKeyPair, issuer: &Url, mut claims: serde_json::Value) -> String {
+	let now = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap()
+		.as_secs();
+	claims["iss"] = issuer.as_str().trim_end_matches('/').into();
+	claims["iat"] = now.into();
+	claims["exp"] = (now + 600).into();
+
+	let signing_input = format!(
+		"{}.{}",
+		base64url(br#"{"alg":"ES256","kid":"stand-in"}"#),
+		base64url(claims.to_string().as_bytes())
+	);
+	let signature = key_pair.sign(&SystemRandom::new(), signing_input.as_bytes()).unwrap();
+	format!("{}.{}", signing_input, base64url(signature.as_ref()))
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use super::signing_keys::SigningKeyCache;
+use crate::utils::{base64url, base64url_decode, AttestationResult};
+
+#[doc = "The protected header of a JSON Web Signature."]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JwsHeader {
+	pub alg: String,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub kid: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub jku: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub typ: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub x5c: Option<Vec<String>>,
+}
+
+/// A JSON Web Signature in compact serialization, split into its decoded parts.
+#[derive(Debug)]
+pub struct Jws<'a> {
+	pub header: JwsHeader,
+	pub claims: Vec<u8>,
+	pub signature: Vec<u8>,
+	signing_input: &'a str,
+}
+
+impl<'a> Jws<'a> {
+	pub fn parse(token: &'a str) -> Result<Jws<'a>, String> {
+		let token = token.trim();
+		let decompose_token: Vec<&str> = token.split('.').collect();
+		if decompose_token.len() != 3 {
+			return Err("JSON Web Tokens must have 3 components delimited by '.' characters.".into())
+		}
+
+		let header = base64url_decode(decompose_token[0])?;
+		let header: JwsHeader = serde_json::from_slice(&header).map_err(|e| e.to_string())?;
+		let claims = base64url_decode(decompose_token[1])?;
+		let signature = base64url_decode(decompose_token[2])?;
+		let signing_input = &token[..decompose_token[0].len() + 1 + decompose_token[1].len()];
+
+		Ok(Jws { header, claims, signature, signing_input })
+	}
+
+	/// Check the signature against the public key of the DER encoded `certificate`.
+	pub fn verify_signature(&self, certificate: &[u8]) -> Result<(), String> {
+		let cert = webpki::EndEntityCert::from(certificate)
+			.map_err(|e| format!("Invalid signing certificate: {:?}", e))?;
+
+		let verified = match self.header.alg.as_str() {
+			"RS256" => cert.verify_signature(
+				&webpki::RSA_PKCS1_2048_8192_SHA256,
+				self.signing_input.as_bytes(),
+				&self.signature,
+			),
+			"ES256" => cert.verify_signature(
+				&webpki::ECDSA_P256_SHA256,
+				self.signing_input.as_bytes(),
+				&ecdsa_signature_to_der(&self.signature)?,
+			),
+			alg => return Err(format!("Unsupported token signing algorithm: {}", alg)),
+		};
+		verified.map_err(|e| format!("Token signature verification failed: {:?}", e))
+	}
+}
+
+/// Wrap `payload` in a JWS with `alg: none` and an empty signature.
+pub fn unsigned_jws(payload: &[u8]) -> String {
+	format!("{}.{}.", base64url(br#"{"alg":"none"}"#), base64url(payload))
+}
+
+/// Decode the claims of a token, without verifying anything about it.
+pub fn decode_token(token: &str) -> Result<AttestationResult, String> {
+	let jws = Jws::parse(token)?;
+	let mut attest_result: AttestationResult =
+		serde_json::from_slice(&jws.claims).map_err(|e| e.to_string())?;
+	attest_result.claims = serde_json::from_slice(&jws.claims).map_err(|e| e.to_string())?;
+	Ok(attest_result)
+}
+
+/// The endpoint among `endpoints` that the token claims to be issued by, whose keys must then
+/// verify it.
+pub fn issuer_endpoint<'a>(token: &str, endpoints: &'a [Url]) -> Result<&'a Url, String> {
+	let iss = decode_token(token)?.iss;
+	let issuer = iss.as_deref().map(|iss| iss.trim_end_matches('/'));
+	endpoints
+		.iter()
+		.find(|endpoint| Some(endpoint.as_str().trim_end_matches('/')) == issuer)
+		.ok_or(format!(
+			"Unexpected token issuer {:?}, expected one of {}",
+			iss,
+			endpoints.iter().map(Url::as_str).collect::<Vec<_>>().join(", ")
+		))
+}
+
+/// Verify a token issued by the provider at `endpoint` and decode its claims.
+///
+/// Checks the signature with the provider key named by the token `kid`, the issuer and the
+/// token lifetime.
+pub fn verify_token(
+	token: &str,
+	endpoint: &Url,
+	keys: &SigningKeyCache,
+) -> Result<AttestationResult, String> {
+	let jws = Jws::parse(token)?;
+	let kid = jws.header.kid.as_deref().ok_or("Token header has no kid")?;
+	let key = keys.get(endpoint, kid)?;
+	jws.verify_signature(&key.certificate()?)?;
+
+	let attest_result = decode_token(token)?;
+
+	let issuer = endpoint.as_str().trim_end_matches('/');
+	match &attest_result.iss {
+		Some(iss) if iss.trim_end_matches('/') == issuer => {},
+		iss => return Err(format!("Unexpected token issuer {:?}, expected {}", iss, issuer)),
+	}
+
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_err(|e| e.to_string())?
+		.as_secs();
+	if attest_result.exp.is_some_and(|exp| exp <= now) {
+		return Err("Token has expired".into())
+	}
+	if attest_result.nbf.is_some_and(|nbf| nbf > now) {
+		return Err("Token is not yet valid".into())
+	}
+
+	Ok(attest_result)
+}
+
+// JWS carries ECDSA signatures as raw `r || s`, webpki expects an ASN.1 `Ecdsa-Sig-Value`.
+fn ecdsa_signature_to_der(signature: &[u8]) -> Result<Vec<u8>, String> {
+	if signature.len() != 64 {
+		return Err(format!("Invalid ES256 signature length: {}", signature.len()))
+	}
+
+	let integer = |bytes: &[u8]| {
+		let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len() - 1);
+		let mut value = bytes[start..].to_vec();
+		if value[0] & 0x80 != 0 {
+			value.insert(0, 0);
+		}
+		let mut der = vec![0x02, value.len() as u8];
+		der.extend(value);
+		der
+	};
+
+	let r = integer(&signature[..32]);
+	let s = integer(&signature[32..]);
+	let mut der = vec![0x30, (r.len() + s.len()) as u8];
+	der.extend(r);
+	der.extend(s);
+	Ok(der)
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	const RESPONSE: &str = include_str!("../../doc/token.json");
+
+	#[test]
+	fn sample_token_decodes() {
+		let response: serde_json::Value = serde_json::from_str(RESPONSE).unwrap();
+		let token = response["token"].as_str().unwrap();
+
+		let jws = Jws::parse(token).unwrap();
+		assert_eq!(jws.header.alg, "RS256");
+		assert_eq!(jws.signature.len(), 256);
+
+		let result = decode_token(token).unwrap();
+		assert_eq!(result.iss.as_deref(), Some("https://testazureprovider.eus.attest.azure.net"));
+		assert_eq!(result.exp, Some(1706181665));
+		assert_eq!(result.x_ms_policy.svn, Some(8888));
+		assert_eq!(result.claims["x-ms-sgx-ehd"], "AQIDBAUG");
+	}
+
+	#[test]
+	fn issuer_is_any_configured_provider() {
+		let response: serde_json::Value = serde_json::from_str(RESPONSE).unwrap();
+		let token = response["token"].as_str().unwrap();
+		let endpoints: Vec<Url> = [
+			"https://testazureprovider.weu.attest.azure.net",
+			"https://testazureprovider.eus.attest.azure.net/",
+		]
+		.iter()
+		.map(|url| Url::parse(url).unwrap())
+		.collect();
+
+		assert_eq!(issuer_endpoint(token, &endpoints), Ok(&endpoints[1]));
+		let err = issuer_endpoint(token, &endpoints[..1]).unwrap_err();
+		assert!(err.contains("expected one of https://testazureprovider.weu.attest.azure.net/"));
+	}
+}
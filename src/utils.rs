@@ -2,21 +2,86 @@ use azure_core::{
 	auth::{AccessToken, TokenCredential},
 	base64, date,
 };
-use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 // use azure_svc_attestation::models::AttestationResult;
 use std::{fs::File, io::Read};
 use time::OffsetDateTime;
 
-use crate::{config::Config, service::maa::MAAPolicy};
+use crate::{config::Config, service::token::Jws};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct AttestationResult {
-	#[serde(rename = "x-ms-policy")]
-	#[serde(flatten)]
-	pub x_ms_policy: MAAPolicy,
+pub use azure_attest_core::{base64url, base64url_decode, AttestationResult};
 
-	#[serde(rename = "x-ms-sgx-ehd", default, skip_serializing_if = "Option::is_none")]
-	pub x_ms_sgx_ehd: Option<String>,
+/// The claims of a token issued against a draft policy.
+///
+/// The service does not sign these tokens, so unlike `AttestationResult` nothing about them is
+/// verified: they only show what a policy would issue, and must never be relied upon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DraftAttestationResult {
+	token: String,
+	claims: Map<String, Value>,
+}
+
+/// How a claim differs between two sets of token claims.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClaimChange {
+	Added(String, Value),
+	Removed(String, Value),
+	Changed(String, Value, Value),
+}
+
+impl std::fmt::Display for ClaimChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ClaimChange::Added(name, value) => write!(f, "+ {}: {}", name, value),
+			ClaimChange::Removed(name, value) => write!(f, "- {}: {}", name, value),
+			ClaimChange::Changed(name, old, new) => write!(f, "~ {}: {} -> {}", name, old, new),
+		}
+	}
+}
+
+// Claims that differ between any two tokens, whatever the policy.
+const VOLATILE_CLAIMS: [&str; 4] = ["exp", "iat", "nbf", "jti"];
+
+impl DraftAttestationResult {
+	pub(crate) fn from_token(token: String) -> Result<Self, String> {
+		let jws = Jws::parse(&token)?;
+		let claims = serde_json::from_slice(&jws.claims).map_err(|e| e.to_string())?;
+		Ok(Self { token, claims })
+	}
+
+	pub fn token(&self) -> &str {
+		&self.token
+	}
+
+	pub fn claims(&self) -> &Map<String, Value> {
+		&self.claims
+	}
+
+	/// How the claims issued under the draft policy differ from `current` ones, ignoring the
+	/// token lifetime and id.
+	pub fn diff(&self, current: &Map<String, Value>) -> Vec<ClaimChange> {
+		let mut changes = Vec::new();
+		for (name, value) in current {
+			match self.claims.get(name) {
+				None => changes.push(ClaimChange::Removed(name.clone(), value.clone())),
+				Some(draft) if draft != value =>
+					changes.push(ClaimChange::Changed(name.clone(), value.clone(), draft.clone())),
+				Some(_) => {},
+			}
+		}
+		for (name, value) in &self.claims {
+			if !current.contains_key(name) {
+				changes.push(ClaimChange::Added(name.clone(), value.clone()));
+			}
+		}
+		changes.retain(|change| {
+			let (ClaimChange::Added(name, _) |
+			ClaimChange::Removed(name, _) |
+			ClaimChange::Changed(name, _, _)) = change;
+			!VOLATILE_CLAIMS.contains(&name.as_str())
+		});
+		changes
+	}
 }
 
 #[derive(Debug)]
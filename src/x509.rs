@@ -0,0 +1,106 @@
+//! Just enough DER to pick the public key and extensions out of X.509 certificates.
+
+struct DerElement<'a> {
+	tag: u8,
+	contents: &'a [u8],
+	// The whole element, tag and length included.
+	encoded: &'a [u8],
+}
+
+// The first element of `input` and what follows it.
+fn der_element(input: &[u8]) -> Option<(DerElement<'_>, &[u8])> {
+	let tag = *input.first()?;
+	let first = *input.get(1)? as usize;
+	let (header, len) = if first < 0x80 {
+		(2, first)
+	} else {
+		let octets = first & 0x7F;
+		if octets == 0 || octets > 4 {
+			return None
+		}
+		let len = input.get(2..2 + octets)?.iter().fold(0usize, |len, b| len << 8 | *b as usize);
+		(2 + octets, len)
+	};
+	let end = header.checked_add(len)?;
+	let encoded = input.get(..end)?;
+	Some((DerElement { tag, contents: &encoded[header..], encoded }, &input[end..]))
+}
+
+fn invalid() -> String {
+	"Invalid X.509 certificate".to_string()
+}
+
+// The fields of the TBSCertificate of `cert`, from the serial number on.
+fn tbs_fields(cert: &[u8]) -> Result<Vec<DerElement<'_>>, String> {
+	let (certificate, _) = der_element(cert).ok_or_else(invalid)?;
+	let (tbs, _) = der_element(certificate.contents).ok_or_else(invalid)?;
+	let mut rest = tbs.contents;
+	// Skip the optional [0] version.
+	if rest.first() == Some(&0xA0) {
+		rest = der_element(rest).ok_or_else(invalid)?.1;
+	}
+	let mut fields = Vec::new();
+	while !rest.is_empty() {
+		let (field, next) = der_element(rest).ok_or_else(invalid)?;
+		fields.push(field);
+		rest = next;
+	}
+	Ok(fields)
+}
+
+/// The DER encoded SubjectPublicKeyInfo of the DER encoded certificate `cert`.
+pub(crate) fn spki(cert: &[u8]) -> Result<&[u8], String> {
+	// After the serial number, signature algorithm, issuer, validity and subject.
+	match tbs_fields(cert)?.get(5) {
+		Some(spki) if spki.tag == 0x30 => Ok(spki.encoded),
+		_ => Err(invalid()),
+	}
+}
+
+/// The value of the extension `oid` of the DER encoded certificate `cert`, if it has it.
+pub(crate) fn extension<'a>(cert: &'a [u8], oid: &[u64]) -> Result<Option<&'a [u8]>, String> {
+	let oid = encode_oid(oid);
+	let Some(extensions) = tbs_fields(cert)?.into_iter().find(|field| field.tag == 0xA3) else {
+		return Ok(None)
+	};
+	let (extensions, _) = der_element(extensions.contents).ok_or_else(invalid)?;
+	let mut rest = extensions.contents;
+	while !rest.is_empty() {
+		let (extension, next) = der_element(rest).ok_or_else(invalid)?;
+		rest = next;
+		// extnID, the optional critical flag, then extnValue.
+		let (id, mut value) = der_element(extension.contents).ok_or_else(invalid)?;
+		if id.tag != 0x06 || id.contents != oid {
+			continue
+		}
+		if value.first() == Some(&0x01) {
+			value = der_element(value).ok_or_else(invalid)?.1;
+		}
+		return match der_element(value) {
+			Some((value, _)) if value.tag == 0x04 => Ok(Some(value.contents)),
+			_ => Err(invalid()),
+		}
+	}
+	Ok(None)
+}
+
+// The contents octets of the DER encoding of the object identifier `oid`.
+fn encode_oid(oid: &[u64]) -> Vec<u8> {
+	let mut arcs = oid.iter().copied();
+	let first = match (arcs.next(), arcs.next()) {
+		(Some(first), Some(second)) => first * 40 + second,
+		(Some(first), None) => first * 40,
+		_ => return Vec::new(),
+	};
+	let mut encoded = Vec::new();
+	for mut arc in std::iter::once(first).chain(arcs) {
+		let mut base128 = vec![(arc & 0x7F) as u8];
+		arc >>= 7;
+		while arc > 0 {
+			base128.push((arc & 0x7F) as u8 | 0x80);
+			arc >>= 7;
+		}
+		encoded.extend(base128.iter().rev());
+	}
+	encoded
+}